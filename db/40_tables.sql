@@ -2,26 +2,68 @@ CREATE TABLE phone.phone
 (
     _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
     phone    VARCHAR(64) NOT NULL,
+    language VARCHAR(2)  NOT NULL DEFAULT 'en',
 
     log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 
     PRIMARY KEY (_id),
     UNIQUE (phone),
-    CHECK (phone ~ '\+[0-9]{2,}')
+    CHECK (phone ~ '\+[0-9]{2,}'),
+    CHECK (language IN ('en', 'sv', 'no'))
 );
 CREATE INDEX ON phone.phone (log_date);
 
 
+CREATE TABLE phone.failed_sms
+(
+    _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
+    phone    VARCHAR(64) NOT NULL,
+    message  TEXT        NOT NULL,
+    error    TEXT        NOT NULL,
+    attempts INT         NOT NULL,
+
+    log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (_id),
+    CHECK (attempts > 0)
+);
+CREATE INDEX ON phone.failed_sms (phone);
+CREATE INDEX ON phone.failed_sms (log_date);
+
+
+-- The latest message of a phone that later parts of a split message are joined with, either still
+-- waiting for its last part or already applied.
+CREATE TABLE phone.pending
+(
+    _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
+    phone    VARCHAR(64) NOT NULL,
+    message  TEXT        NOT NULL,
+    applied  BOOL        NOT NULL,
+
+    log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (_id),
+    UNIQUE (phone)
+);
+CREATE INDEX ON phone.pending (log_date);
+
+
+
 CREATE TABLE hike.hike
 (
-    _id       UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
-    _phone_id UUID        NOT NULL,
+    _id        UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
+    _phone_id  UUID        NOT NULL,
+    name       TEXT,
+    party      INT,
+    tent       TEXT,
+    experience TEXT,
 
-    log_date  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    log_date   TIMESTAMPTZ NOT NULL DEFAULT NOW(),
 
     PRIMARY KEY (_id),
     FOREIGN KEY (_phone_id) REFERENCES phone.phone (_id) ON DELETE CASCADE,
-    UNIQUE (_phone_id)
+    UNIQUE (_phone_id),
+    CHECK (party > 0)
 );
 CREATE INDEX ON hike.hike (log_date);
 
@@ -41,12 +83,13 @@ VALUES (0, 'Food'),
        (2, 'Hut');
 
 
+-- Revisions are ordered by the time they were made, also within a transaction.
 CREATE TABLE hike.route
 (
     _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
     _hike_id UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
 
-    log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    log_date TIMESTAMPTZ NOT NULL DEFAULT CLOCK_TIMESTAMP(),
 
     PRIMARY KEY (_id),
     FOREIGN KEY (_hike_id) REFERENCES hike.hike (_id) ON DELETE CASCADE
@@ -59,19 +102,27 @@ CREATE TABLE hike.route_point
 (
     _id        UUID                   NOT NULL DEFAULT uuid.uuid_generate_v4(),
     _route_id  UUID                   NOT NULL,
+    ordinal    INT                    NOT NULL,
     _action_id SMALLINT,
     message    TEXT,
     date       DATE                   NOT NULL,
     time       TIME,
     geom       GEOMETRY(Point, 25833) NOT NULL,
+    srid       INT                    NOT NULL,
+    precision  INT,
+    place      TEXT,
+    state      VARCHAR(16)            NOT NULL DEFAULT 'pending',
 
     log_date   TIMESTAMPTZ            NOT NULL DEFAULT NOW(),
 
     PRIMARY KEY (_id),
     FOREIGN KEY (_route_id) REFERENCES hike.route (_id) ON DELETE CASCADE,
     FOREIGN KEY (_action_id) REFERENCES hike.action (_id) ON DELETE CASCADE,
+    UNIQUE (_route_id, ordinal),
+    CHECK (state IN ('pending', 'reached', 'skipped')),
     CHECK (-100000 < ST_X(geom) AND ST_X(geom) < 1350000 AND
-           6070000 < ST_Y(geom) AND ST_Y(geom) < 7960000)
+           6070000 < ST_Y(geom) AND ST_Y(geom) < 7960000),
+    CHECK (precision > 0)
 );
 CREATE INDEX ON hike.route_point USING GIST (geom);
 CREATE INDEX ON hike.route_point (date);
@@ -93,25 +144,73 @@ CREATE INDEX ON hike.trace (_hike_id);
 CREATE INDEX ON hike.trace (log_date);
 
 
+-- A checkin that marked a stop reached refers to the route revision it made, so that both are
+-- undone together.
 CREATE TABLE hike.trace_point
 (
     _id        UUID                   NOT NULL DEFAULT uuid.uuid_generate_v4(),
     _trace_id  UUID                   NOT NULL,
     _action_id SMALLINT,
+    _route_id  UUID,
     message    TEXT,
     date       DATE,
     time       TIME,
     geom       GEOMETRY(Point, 25833) NOT NULL,
+    srid       INT                    NOT NULL,
+    precision  INT,
+    place      TEXT,
+    sos        BOOL                   NOT NULL DEFAULT FALSE,
 
-    log_date   TIMESTAMPTZ            NOT NULL DEFAULT NOW(),
+    log_date   TIMESTAMPTZ            NOT NULL DEFAULT CLOCK_TIMESTAMP(),
 
     PRIMARY KEY (_id),
     FOREIGN KEY (_trace_id) REFERENCES hike.trace (_id) ON DELETE CASCADE,
     FOREIGN KEY (_action_id) REFERENCES hike.action (_id) ON DELETE CASCADE,
+    FOREIGN KEY (_route_id) REFERENCES hike.route (_id) ON DELETE SET NULL,
     CHECK (-100000 < ST_X(geom) AND ST_X(geom) < 1350000 AND
-           6070000 < ST_Y(geom) AND ST_Y(geom) < 7960000)
+           6070000 < ST_Y(geom) AND ST_Y(geom) < 7960000),
+    CHECK (precision > 0)
 );
 CREATE INDEX ON hike.trace_point USING GIST (geom);
 CREATE INDEX ON hike.trace_point (date);
 CREATE INDEX ON hike.trace_point (time);
-CREATE INDEX ON hike.trace_point (log_date);
\ No newline at end of file
+CREATE INDEX ON hike.trace_point (log_date);
+
+
+-- Emergency contacts are only alerted once they have accepted.
+CREATE TABLE hike.contact
+(
+    _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
+    _hike_id UUID        NOT NULL,
+    phone    VARCHAR(64) NOT NULL,
+    name     TEXT,
+    active   BOOL        NOT NULL DEFAULT FALSE,
+
+    log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (_id),
+    FOREIGN KEY (_hike_id) REFERENCES hike.hike (_id) ON DELETE CASCADE,
+    UNIQUE (_hike_id, phone),
+    CHECK (phone ~ '\+[0-9]{2,}')
+);
+CREATE INDEX ON hike.contact (_hike_id);
+CREATE INDEX ON hike.contact (phone);
+CREATE INDEX ON hike.contact (log_date);
+
+-- The highest escalation level reached for a planned stop that was missed.
+CREATE TABLE hike.alert
+(
+    _id      UUID        NOT NULL DEFAULT uuid.uuid_generate_v4(),
+    _hike_id UUID        NOT NULL,
+    planned  TIMESTAMP   NOT NULL,
+    level    INT         NOT NULL,
+
+    log_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (_id),
+    FOREIGN KEY (_hike_id) REFERENCES hike.hike (_id) ON DELETE CASCADE,
+    UNIQUE (_hike_id, planned),
+    CHECK (level > 0)
+);
+CREATE INDEX ON hike.alert (_hike_id);
+CREATE INDEX ON hike.alert (log_date);
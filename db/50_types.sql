@@ -1,8 +1,10 @@
 DROP TYPE IF EXISTS interface.point_t CASCADE;
 CREATE TYPE interface.point_t AS (
-    eastings_ REAL,
-    northings_ REAL,
+    eastings_ DOUBLE PRECISION,
+    northings_ DOUBLE PRECISION,
     srid_ INT,
+    precision_ INT,
+    place_ TEXT,
     action_ VARCHAR(64),
     message_ TEXT,
     date_ DATE,
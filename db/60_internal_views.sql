@@ -17,9 +17,13 @@ SELECT route._hike_id,
                                            'message',
                                            route_point.message,
                                            'date', route_point.date,
-                                           'time', route_point.time
+                                           'time', route_point.time,
+                                           'precision',
+                                           route_point.precision,
+                                           'place', route_point.place,
+                                           'state', route_point.state
                                        )
-                               ))
+                               ) ORDER BY route_point.ordinal)
                )
            ELSE NULL END AS geojson
 FROM hike.route
@@ -47,9 +51,14 @@ SELECT trace._hike_id,
                                            'message',
                                            trace_point.message,
                                            'date', trace_point.date,
-                                           'time', trace_point.time
+                                           'time', trace_point.time,
+                                           'precision',
+                                           trace_point.precision,
+                                           'place', trace_point.place,
+                                           'sos', trace_point.sos,
+                                           'log_date', trace_point.log_date
                                        )
-                               ))
+                               ) ORDER BY trace_point.log_date)
                )
            ELSE NULL END AS geojson
 FROM hike.trace
@@ -62,7 +71,13 @@ CREATE OR REPLACE VIEW interface.hike AS
 SELECT hike._id,
        phone.phone,
        route.geojson as route,
-       trace.geojson as trace
+       trace.geojson as trace,
+       JSONB_BUILD_OBJECT(
+               'name', hike.name,
+               'party', hike.party,
+               'tent', hike.tent,
+               'experience', hike.experience
+           )         as info
 FROM hike.hike
          JOIN phone.phone ON hike.hike._phone_id = phone.phone._id
          LEFT JOIN (SELECT *
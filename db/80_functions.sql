@@ -1,3 +1,20 @@
+DROP FUNCTION IF EXISTS interface.srid_allowed(srid_ INT);
+CREATE OR REPLACE FUNCTION interface.srid_allowed(srid_ INT)
+    RETURNS BOOL
+AS
+$$
+SELECT srid_ = ANY (ARRAY [
+    4326, -- WGS 84
+    25832, 25833, 25834, 25835, -- ETRS89 / UTM 32N-35N
+    3006, -- SWEREF99 TM
+    3021, -- RT90 2.5 gon V
+    5105, 5106, 5107, 5108, 5109, 5110, 5111, 5112, 5113, 5114, 5115, 5116, 5117,
+    5118, 5119, 5120, 5121, 5122, 5123, 5124, 5125, 5126, 5127, 5128, 5129,
+    5130 -- ETRS89 / NTM 5-30
+    ]);
+$$ language sql IMMUTABLE;
+
+
 DROP FUNCTION IF EXISTS public.create_hike(phone_ VARCHAR(64), points_ JSONB);
 CREATE OR REPLACE FUNCTION public.create_hike(phone_ VARCHAR(64), points_ JSONB)
     RETURNS SETOF interface.hike
@@ -30,10 +47,8 @@ CREATE OR REPLACE FUNCTION public.edit_route(phone_ VARCHAR(64), points_ JSONB)
 AS
 $$
 DECLARE
-    hike_row_   hike.hike%ROWTYPE;
-    route_row_  hike.route%ROWTYPE;
-    action_row_ hike.action%ROWTYPE;
-    point_      interface.point_t;
+    hike_row_  hike.hike%ROWTYPE;
+    route_row_ hike.route%ROWTYPE;
 BEGIN
     SELECT hike.*
     INTO hike_row_
@@ -48,17 +63,36 @@ BEGIN
     INSERT INTO hike.route (_hike_id)
     VALUES (hike_row_._id) RETURNING * INTO route_row_;
 
+    PERFORM interface.add_route_points(route_row_._id, points_, 0);
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike_row_._id = hike._id;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Inserts the points into the route, numbered after the given ordinal.
+DROP FUNCTION IF EXISTS interface.add_route_points(route_id_ UUID, points_ JSONB, ordinal_ INT);
+CREATE OR REPLACE FUNCTION interface.add_route_points(route_id_ UUID, points_ JSONB, ordinal_ INT)
+    RETURNS VOID
+AS
+$$
+DECLARE
+    action_row_ hike.action%ROWTYPE;
+    point_      interface.point_t;
+BEGIN
     FOR point_ IN
         SELECT value -> 'position' -> 'eastings'  AS eastings_,
                value -> 'position' -> 'northings' AS northings_,
                value -> 'position' -> 'srid'      AS srid_,
+               value -> 'position' -> 'precision' AS precision_,
+               value -> 'position' ->> 'place'    AS place_,
                value ->> 'action'                 AS action_,
                value ->> 'message'                AS message_,
                value ->> 'date'                   AS date_,
                value ->> 'time'                   AS time_
         FROM jsonb_array_elements(points_)
         LOOP
-            IF point_.srid_ < 25832 OR point_.srid_ > 25835 THEN
+            IF NOT interface.srid_allowed(point_.srid_) THEN
                 RAISE EXCEPTION 'Invalid SRID supplied!';
             END IF;
 
@@ -71,29 +105,179 @@ BEGIN
                 RAISE EXCEPTION 'Invalid action supplied!';
             END IF;
 
-            INSERT INTO hike.route_point (_route_id, _action_id, message, date,
-                                          time, geom)
-            VALUES (route_row_._id, action_row_._id, point_.message_,
+            ordinal_ := ordinal_ + 1;
+
+            INSERT INTO hike.route_point (_route_id, ordinal, _action_id, message,
+                                          date, time, geom, srid, precision,
+                                          place)
+            VALUES (route_id_, ordinal_, action_row_._id, point_.message_,
                     point_.date_, point_.time_,
                     ST_Transform(ST_SetSRID(
                                          ST_MakePoint(point_.eastings_,
                                                       point_.northings_),
-                                         point_.srid_), 25833));
+                                         point_.srid_), 25833),
+                    point_.srid_, point_.precision_, point_.place_);
 
         END LOOP;
-    RETURN QUERY SELECT * FROM interface.hike WHERE hike_row_._id = hike._id;
 END;
 $$ language plpgsql VOLATILE
                     SECURITY DEFINER;
 
 
-DROP FUNCTION IF EXISTS public.checkin_trace(phone_ VARCHAR(64), point_ JSONB);
-CREATE OR REPLACE FUNCTION public.checkin_trace(phone_ VARCHAR(64), point_json_ JSONB)
+-- Makes a new revision of the latest route of the hike, copying its points except the one at
+-- skip_, and moving the points from from_ onwards shift_ places along. The earlier revisions are
+-- kept as they were. Returns the new route, and the number of points in the previous one.
+DROP FUNCTION IF EXISTS interface.revise_route(hike_id_ UUID, skip_ INT, from_ INT, shift_ INT);
+CREATE OR REPLACE FUNCTION interface.revise_route(hike_id_ UUID, skip_ INT, from_ INT, shift_ INT)
+    RETURNS TABLE
+            (
+                _route_id UUID,
+                stops     INT
+            )
+AS
+$$
+DECLARE
+    previous_id_ UUID;
+    route_row_   hike.route%ROWTYPE;
+    stops_       INT;
+BEGIN
+    SELECT route._id
+    INTO previous_id_
+    FROM hike.route
+    WHERE route._hike_id = hike_id_
+    ORDER BY route.log_date DESC
+    LIMIT 1;
+
+    IF previous_id_ IS NULL THEN
+        RAISE EXCEPTION 'Route does not exist for given hike!';
+    END IF;
+
+    SELECT COUNT(*)
+    INTO stops_
+    FROM hike.route_point
+    WHERE route_point._route_id = previous_id_;
+
+    IF skip_ IS NOT NULL AND NOT skip_ BETWEEN 1 AND stops_ THEN
+        RAISE EXCEPTION 'Stop does not exist in route!';
+    END IF;
+    IF from_ IS NOT NULL AND NOT from_ BETWEEN 1 AND stops_ + 1 THEN
+        RAISE EXCEPTION 'Stop does not exist in route!';
+    END IF;
+
+    INSERT INTO hike.route (_hike_id)
+    VALUES (hike_id_) RETURNING * INTO route_row_;
+
+    INSERT INTO hike.route_point (_route_id, ordinal, _action_id, message, date, time, geom,
+                                  srid, precision, place, state)
+    SELECT route_row_._id,
+           CASE
+               WHEN route_point.ordinal >= from_ THEN route_point.ordinal + shift_
+               ELSE route_point.ordinal
+               END,
+           route_point._action_id,
+           route_point.message,
+           route_point.date,
+           route_point.time,
+           route_point.geom,
+           route_point.srid,
+           route_point.precision,
+           route_point.place,
+           route_point.state
+    FROM hike.route_point
+    WHERE route_point._route_id = previous_id_
+      AND route_point.ordinal IS DISTINCT FROM skip_;
+
+    RETURN QUERY SELECT route_row_._id, stops_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS interface.phone_hike(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION interface.phone_hike(phone_ VARCHAR(64))
+    RETURNS UUID
+AS
+$$
+DECLARE
+    hike_id_ UUID;
+BEGIN
+    SELECT hike._id
+    INTO hike_id_
+    FROM hike.hike
+             INNER JOIN phone.phone ON hike._phone_id = phone._id
+    WHERE phone.phone = phone_;
+
+    IF hike_id_ IS NULL THEN
+        RAISE EXCEPTION 'Hike does not exist for given phone number!';
+    END IF;
+    RETURN hike_id_;
+END;
+$$ language plpgsql STABLE
+                    SECURITY DEFINER;
+
+
+-- Appends the points to the route.
+DROP FUNCTION IF EXISTS public.add_route(phone_ VARCHAR(64), points_ JSONB);
+CREATE OR REPLACE FUNCTION public.add_route(phone_ VARCHAR(64), points_ JSONB)
     RETURNS SETOF interface.hike
 AS
 $$
 DECLARE
-    hike_row_   hike.hike%ROWTYPE;
+    hike_id_  UUID := interface.phone_hike(phone_);
+    revision_ RECORD;
+BEGIN
+    SELECT * INTO revision_ FROM interface.revise_route(hike_id_, NULL, NULL, 0);
+    PERFORM interface.add_route_points(revision_._route_id, points_, revision_.stops);
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Inserts the points before the given stop of the route, as [stop, points].
+DROP FUNCTION IF EXISTS public.insert_route(phone_ VARCHAR(64), insertion_ JSONB);
+CREATE OR REPLACE FUNCTION public.insert_route(phone_ VARCHAR(64), insertion_ JSONB)
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_id_  UUID  := interface.phone_hike(phone_);
+    stop_     INT   := (insertion_ ->> 0)::INT;
+    points_   JSONB := insertion_ -> 1;
+    revision_ RECORD;
+BEGIN
+    SELECT *
+    INTO revision_
+    FROM interface.revise_route(hike_id_, NULL, stop_, jsonb_array_length(points_));
+    PERFORM interface.add_route_points(revision_._route_id, points_, stop_ - 1);
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.remove_route(phone_ VARCHAR(64), stop_ JSONB);
+CREATE OR REPLACE FUNCTION public.remove_route(phone_ VARCHAR(64), stop_ JSONB)
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_id_ UUID := interface.phone_hike(phone_);
+    ordinal_ INT  := (stop_ #>> '{}')::INT;
+BEGIN
+    PERFORM interface.revise_route(hike_id_, ordinal_, ordinal_ + 1, -1);
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS interface.add_trace_point(hike_id_ UUID, point_json_ JSONB, sos_ BOOL);
+CREATE OR REPLACE FUNCTION interface.add_trace_point(hike_id_ UUID, point_json_ JSONB, sos_ BOOL)
+    RETURNS VOID
+AS
+$$
+DECLARE
     trace_row_  hike.trace%ROWTYPE;
     action_row_ hike.action%ROWTYPE;
     point_      interface.point_t;
@@ -101,36 +285,28 @@ BEGIN
     SELECT point_json_ -> 'position' -> 'eastings'  AS eastings_,
            point_json_ -> 'position' -> 'northings' AS northings_,
            point_json_ -> 'position' -> 'srid'      AS srid_,
+           point_json_ -> 'position' -> 'precision' AS precision_,
+           point_json_ -> 'position' ->> 'place'    AS place_,
            point_json_ ->> 'action'                 AS action_,
            point_json_ ->> 'message'                AS message_,
            point_json_ ->> 'date'                   AS date_,
            point_json_ ->> 'time'                   AS time_
     INTO point_;
 
-    IF point_.srid_ < 25832 OR point_.srid_ > 25835 THEN
+    IF NOT interface.srid_allowed(point_.srid_) THEN
         RAISE EXCEPTION 'Invalid SRID supplied!';
     END IF;
 
-    SELECT hike.*
-    INTO hike_row_
-    FROM hike.hike
-             INNER JOIN phone.phone ON hike._phone_id = phone._id
-    WHERE phone.phone = phone_;
-
-    IF hike_row_._id IS NULL THEN
-        RAISE EXCEPTION 'Hike does not exist for given phone number!';
-    END IF;
-
     SELECT *
     INTO trace_row_
     FROM hike.trace
-    WHERE trace._hike_id = hike_row_._id
+    WHERE trace._hike_id = hike_id_
     ORDER BY trace.log_date DESC
     LIMIT 1;
 
     IF trace_row_._id IS NULL THEN
         INSERT INTO hike.trace (_hike_id)
-        VALUES (hike_row_._id) RETURNING * INTO trace_row_;
+        VALUES (hike_id_) RETURNING * INTO trace_row_;
     END IF;
 
     SELECT *
@@ -139,14 +315,44 @@ BEGIN
     WHERE action.action = point_.action_;
 
     INSERT INTO hike.trace_point (_trace_id, _action_id, message, date, time,
-                                  geom)
+                                  geom, srid, precision, place, sos)
     VALUES (trace_row_._id, action_row_._id, point_.message_,
             point_.date_, point_.time_,
             ST_Transform(ST_SetSRID(ST_MakePoint(
                                             point_.eastings_,
                                             point_.northings_),
                                     point_.srid_),
-                         25833));
+                         25833),
+            point_.srid_, point_.precision_, point_.place_, sos_);
+END ;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER
+                    -- PostGIS is installed in public; temporary objects must not shadow it.
+                    SET search_path = public, pg_temp;
+
+
+DROP FUNCTION IF EXISTS public.checkin_trace(phone_ VARCHAR(64), checkin_ JSONB);
+CREATE OR REPLACE FUNCTION public.checkin_trace(phone_ VARCHAR(64), checkin_ JSONB)
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_row_ hike.hike%ROWTYPE;
+BEGIN
+    SELECT hike.*
+    INTO hike_row_
+    FROM hike.hike
+             INNER JOIN phone.phone ON hike._phone_id = phone._id
+    WHERE phone.phone = phone_;
+
+    IF hike_row_._id IS NULL THEN
+        RAISE EXCEPTION 'Hike does not exist for given phone number!';
+    END IF;
+
+    PERFORM interface.add_trace_point(hike_row_._id, checkin_ -> 'point', FALSE);
+    IF (checkin_ ->> 'reached')::BOOL THEN
+        PERFORM interface.reach_nearest_stop(hike_row_._id);
+    END IF;
 
     RETURN QUERY SELECT * FROM interface.hike WHERE hike_row_._id = hike._id;
 END ;
@@ -154,19 +360,100 @@ $$ language plpgsql VOLATILE
                     SECURITY DEFINER;
 
 
+-- Records the position of a distress call, if given, and returns it, or else the last known
+-- position, as WGS 84 and as UTM in the zone of its longitude. A call without a hike is not
+-- recorded, but still located.
+DROP FUNCTION IF EXISTS public.sos(phone_ VARCHAR(64), sos_ JSONB);
+CREATE OR REPLACE FUNCTION public.sos(phone_ VARCHAR(64), sos_ JSONB)
+    RETURNS TABLE
+            (
+                latitude  DOUBLE PRECISION,
+                longitude DOUBLE PRECISION,
+                zone      INT,
+                eastings  DOUBLE PRECISION,
+                northings DOUBLE PRECISION
+            )
+AS
+$$
+DECLARE
+    hike_row_  hike.hike%ROWTYPE;
+    point_row_ hike.trace_point%ROWTYPE;
+    geom_      GEOMETRY;
+BEGIN
+    SELECT hike.*
+    INTO hike_row_
+    FROM hike.hike
+             INNER JOIN phone.phone ON hike._phone_id = phone._id
+    WHERE phone.phone = phone_;
+
+    IF jsonb_typeof(sos_ -> 'point' -> 'position' -> 'srid') = 'number' THEN
+        IF NOT interface.srid_allowed((sos_ -> 'point' -> 'position' ->> 'srid')::INT) THEN
+            RAISE EXCEPTION 'Invalid SRID supplied!';
+        END IF;
+
+        geom_ := ST_SetSRID(
+                ST_MakePoint(
+                        (sos_ -> 'point' -> 'position' ->> 'eastings')::DOUBLE PRECISION,
+                        (sos_ -> 'point' -> 'position' ->> 'northings')::DOUBLE PRECISION),
+                (sos_ -> 'point' -> 'position' ->> 'srid')::INT);
+
+        IF hike_row_._id IS NOT NULL THEN
+            PERFORM interface.add_trace_point(
+                    hike_row_._id,
+                    (sos_ -> 'point') || jsonb_build_object('message', sos_ -> 'message'),
+                    TRUE);
+        END IF;
+    ELSE
+        SELECT trace_point.*
+        INTO point_row_
+        FROM hike.trace_point
+                 INNER JOIN hike.trace ON trace_point._trace_id = trace._id
+        WHERE trace._hike_id = hike_row_._id
+        ORDER BY trace_point.log_date DESC
+        LIMIT 1;
+
+        -- Without a position, the call is recorded where the hiker was last known to be.
+        geom_ := point_row_.geom;
+        IF geom_ IS NOT NULL THEN
+            INSERT INTO hike.trace_point (_trace_id, message, geom, srid, precision, place, sos)
+            VALUES (point_row_._trace_id, sos_ ->> 'message', point_row_.geom, point_row_.srid,
+                    point_row_.precision, point_row_.place, TRUE);
+        END IF;
+    END IF;
+
+    IF geom_ IS NULL THEN
+        RETURN;
+    END IF;
+
+    geom_ := ST_Transform(geom_, 4326);
+    zone := FLOOR((ST_X(geom_) + 180) / 6)::INT + 1;
+    latitude := ST_Y(geom_);
+    longitude := ST_X(geom_);
+    geom_ := ST_Transform(geom_, 32600 + zone);
+    eastings := ST_X(geom_);
+    northings := ST_Y(geom_);
+    RETURN NEXT;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
 DROP FUNCTION IF EXISTS public.complete_hike(phone_ VARCHAR(64));
 CREATE OR REPLACE FUNCTION public.complete_hike(phone_ VARCHAR(64))
     RETURNS BOOL
 AS
 $$
 DECLARE
-    phone_row_ phone.phone%ROWTYPE;
+    hike_row_ hike.hike%ROWTYPE;
 BEGIN
+    -- The phone is kept, as it remembers the language of the sender.
     DELETE
-    FROM phone.phone
-    WHERE phone_ = phone.phone RETURNING * INTO phone_row_;
+    FROM hike.hike
+        USING phone.phone
+    WHERE hike._phone_id = phone._id
+      AND phone_ = phone.phone RETURNING hike.* INTO hike_row_;
 
-    IF phone_row_._id IS NULL THEN
+    IF hike_row_._id IS NULL THEN
         RETURN FALSE;
     END IF;
     RETURN TRUE;
@@ -175,6 +462,164 @@ $$ language plpgsql VOLATILE
                     SECURITY DEFINER;
 
 
+-- Returns a row for each hike the change concerns, with its hiker and their language, and
+-- whether the contact has yet to accept; no row if nothing changed. An added contact is pending
+-- until the contact accepts, which accepts every pending request for their phone number.
+DROP FUNCTION IF EXISTS public.edit_contact(phone_ VARCHAR(64), contact_ JSONB);
+CREATE OR REPLACE FUNCTION public.edit_contact(phone_ VARCHAR(64), contact_ JSONB)
+    RETURNS TABLE
+            (
+                hiker    VARCHAR(64),
+                language VARCHAR(2),
+                pending  BOOL
+            )
+AS
+$$
+DECLARE
+    hike_row_ hike.hike%ROWTYPE;
+BEGIN
+    IF contact_ ->> 'change' = 'accept' THEN
+        RETURN QUERY WITH accepted_ AS (UPDATE hike.contact
+            SET active = TRUE
+            WHERE contact.phone = phone_
+                AND NOT contact.active
+            RETURNING contact._hike_id)
+                     SELECT phone.phone, phone.language, FALSE
+                     FROM accepted_
+                              INNER JOIN hike.hike ON accepted_._hike_id = hike._id
+                              INNER JOIN phone.phone ON hike._phone_id = phone._id;
+        RETURN;
+    END IF;
+
+    SELECT hike.*
+    INTO hike_row_
+    FROM hike.hike
+             INNER JOIN phone.phone ON hike._phone_id = phone._id
+    WHERE phone.phone = phone_;
+
+    IF hike_row_._id IS NULL THEN
+        RAISE EXCEPTION 'Hike does not exist for given phone number!';
+    END IF;
+
+    IF contact_ ->> 'change' = 'remove' THEN
+        RETURN QUERY WITH removed_ AS (DELETE
+            FROM hike.contact
+            WHERE contact._hike_id = hike_row_._id
+                AND contact.phone = contact_ ->> 'phone'
+            RETURNING contact._id)
+                     SELECT phone_, NULL::VARCHAR(2), FALSE
+                     FROM removed_;
+        RETURN;
+    END IF;
+
+    RETURN QUERY WITH added_ AS (INSERT INTO hike.contact (_hike_id, phone, name)
+        VALUES (hike_row_._id, contact_ ->> 'phone', contact_ ->> 'name')
+        ON CONFLICT (_hike_id, phone) DO UPDATE SET name = EXCLUDED.name
+        RETURNING contact.active)
+                 SELECT phone_, NULL::VARCHAR(2), NOT added_.active
+                 FROM added_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Hikes may be viewed by their hiker and by the contacts that accepted.
+DROP FUNCTION IF EXISTS public.may_view(viewer_ VARCHAR(64), phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.may_view(viewer_ VARCHAR(64), phone_ VARCHAR(64))
+    RETURNS BOOL
+AS
+$$
+SELECT viewer_ = phone_
+           OR EXISTS(SELECT
+                     FROM hike.contact
+                              INNER JOIN hike.hike ON contact._hike_id = hike._id
+                              INNER JOIN phone.phone ON hike._phone_id = phone._id
+                     WHERE phone.phone = phone_
+                       AND contact.phone = viewer_
+                       AND contact.active);
+$$ language sql SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.set_language(phone_ VARCHAR(64), language_ VARCHAR(2));
+CREATE OR REPLACE FUNCTION public.set_language(phone_ VARCHAR(64), language_ VARCHAR(2))
+    RETURNS VOID
+AS
+$$
+INSERT INTO phone.phone (phone, language)
+VALUES (phone_, language_)
+ON CONFLICT (phone) DO UPDATE SET language = EXCLUDED.language;
+$$ language sql VOLATILE
+                SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.record_failed_sms(phone_ VARCHAR(64), message_ TEXT, error_ TEXT,
+                                                 attempts_ INT);
+CREATE OR REPLACE FUNCTION public.record_failed_sms(phone_ VARCHAR(64), message_ TEXT, error_ TEXT,
+                                                   attempts_ INT)
+    RETURNS VOID
+AS
+$$
+INSERT INTO phone.failed_sms (phone, message, error, attempts)
+VALUES (phone_, message_, error_, attempts_);
+$$ language sql VOLATILE
+                SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.pending_message(phone_ VARCHAR(64), minutes_ INT);
+CREATE OR REPLACE FUNCTION public.pending_message(phone_ VARCHAR(64), minutes_ INT)
+    RETURNS TABLE
+            (
+                message TEXT,
+                applied BOOL
+            )
+AS
+$$
+SELECT pending.message, pending.applied
+FROM phone.pending
+WHERE pending.phone = phone_
+  AND pending.log_date >= NOW() - make_interval(mins => minutes_);
+$$ language sql SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.set_pending_message(phone_ VARCHAR(64), message_ TEXT,
+                                                   applied_ BOOL);
+CREATE OR REPLACE FUNCTION public.set_pending_message(phone_ VARCHAR(64), message_ TEXT,
+                                                     applied_ BOOL)
+    RETURNS VOID
+AS
+$$
+INSERT INTO phone.pending (phone, message, applied)
+VALUES (phone_, message_, applied_)
+ON CONFLICT (phone) DO UPDATE SET message  = EXCLUDED.message,
+                                  applied  = EXCLUDED.applied,
+                                  log_date = NOW();
+$$ language sql VOLATILE
+                SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.clear_pending_message(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.clear_pending_message(phone_ VARCHAR(64))
+    RETURNS VOID
+AS
+$$
+DELETE
+FROM phone.pending
+WHERE pending.phone = phone_;
+$$ language sql VOLATILE
+                SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.language(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.language(phone_ VARCHAR(64))
+    RETURNS VARCHAR(2)
+AS
+$$
+SELECT phone.language
+FROM phone.phone
+WHERE phone.phone = phone_;
+$$ language sql SECURITY DEFINER;
+
+
 DROP FUNCTION IF EXISTS public.hike(phone_ VARCHAR(64));
 CREATE OR REPLACE FUNCTION public.hike(phone_ VARCHAR(64))
     RETURNS SETOF interface.hike
@@ -186,3 +631,450 @@ BEGIN
                  WHERE phone = phone_;
 END;
 $$ language plpgsql SECURITY DEFINER;
+
+
+
+-- Locates the latest checkin along the current route: its distance from the route, and the first
+-- planned stop after it together with the distance to that stop along the route, in metres.
+DROP FUNCTION IF EXISTS public.checkin_status(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.checkin_status(phone_ VARCHAR(64))
+    RETURNS TABLE
+            (
+                distance  DOUBLE PRECISION,
+                remaining DOUBLE PRECISION,
+                place     TEXT,
+                message   TEXT,
+                date      DATE,
+                time      TIME
+            )
+AS
+$$
+WITH hike_ AS (SELECT hike._id
+               FROM hike.hike
+                        INNER JOIN phone.phone ON hike._phone_id = phone._id
+               WHERE phone.phone = phone_),
+     checkin_ AS (SELECT trace_point.geom
+                  FROM hike.trace_point
+                           INNER JOIN hike.trace ON trace_point._trace_id = trace._id
+                  WHERE trace._hike_id IN (SELECT _id FROM hike_)
+                  ORDER BY trace_point.log_date DESC
+                  LIMIT 1),
+     route_ AS (SELECT route._id
+                FROM hike.route
+                WHERE route._hike_id IN (SELECT _id FROM hike_)
+                ORDER BY route.log_date DESC
+                LIMIT 1),
+     points_ AS (SELECT route_point.*
+                 FROM hike.route_point
+                 WHERE route_point._route_id IN (SELECT _id FROM route_)),
+     line_ AS (SELECT ST_MakeLine(points_.geom ORDER BY points_.ordinal) AS geom
+               FROM points_),
+     located_ AS (SELECT checkin_.geom,
+                         line_.geom                                      AS line,
+                         ST_LineLocatePoint(line_.geom, checkin_.geom) AS fraction
+                  FROM checkin_
+                           CROSS JOIN line_
+                  WHERE ST_NPoints(line_.geom) > 1)
+SELECT ST_Distance(located_.geom, located_.line),
+       ST_Length(located_.line) * (next_.fraction - located_.fraction),
+       next_.place,
+       next_.message,
+       next_.date,
+       next_.time
+FROM located_
+         LEFT JOIN LATERAL (SELECT points_.*,
+                                   ST_LineLocatePoint(located_.line, points_.geom) AS fraction
+                            FROM points_
+                            WHERE ST_LineLocatePoint(located_.line, points_.geom) >
+                                  located_.fraction
+                              AND points_.state = 'pending'
+                            ORDER BY points_.ordinal
+                            LIMIT 1) next_ ON TRUE;
+$$ language sql SECURITY DEFINER;
+
+
+-- The latest checkin of the hike, from its own traces. A hike without checkins gives a row of
+-- nulls, and a phone without a hike gives no row.
+DROP FUNCTION IF EXISTS public.last_checkin(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.last_checkin(phone_ VARCHAR(64))
+    RETURNS TABLE
+            (
+                log_date TIMESTAMPTZ,
+                place    TEXT,
+                message  TEXT
+            )
+AS
+$$
+SELECT checkin_.log_date,
+       checkin_.place,
+       checkin_.message
+FROM hike.hike
+         INNER JOIN phone.phone ON hike._phone_id = phone._id
+         LEFT JOIN LATERAL (SELECT trace_point.*
+                            FROM hike.trace_point
+                                     INNER JOIN hike.trace ON trace_point._trace_id = trace._id
+                            WHERE trace._hike_id = hike._id
+                            ORDER BY trace_point.log_date DESC
+                            LIMIT 1) checkin_ ON TRUE
+WHERE phone.phone = phone_;
+$$ language sql SECURITY DEFINER;
+
+
+-- The first planned stop of the current route, for hikes not yet checked in along it, together
+-- with its distance from the start along the route, in metres.
+DROP FUNCTION IF EXISTS public.next_stop(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.next_stop(phone_ VARCHAR(64))
+    RETURNS TABLE
+            (
+                remaining DOUBLE PRECISION,
+                place     TEXT,
+                message   TEXT,
+                date      DATE,
+                time      TIME
+            )
+AS
+$$
+WITH hike_ AS (SELECT hike._id
+               FROM hike.hike
+                        INNER JOIN phone.phone ON hike._phone_id = phone._id
+               WHERE phone.phone = phone_),
+     route_ AS (SELECT route._id
+                FROM hike.route
+                WHERE route._hike_id IN (SELECT _id FROM hike_)
+                ORDER BY route.log_date DESC
+                LIMIT 1),
+     points_ AS (SELECT route_point.*
+                 FROM hike.route_point
+                 WHERE route_point._route_id IN (SELECT _id FROM route_)),
+     line_ AS (SELECT ST_MakeLine(points_.geom ORDER BY points_.ordinal) AS geom
+               FROM points_)
+SELECT CASE
+           WHEN ST_NPoints(line_.geom) > 1
+               THEN ST_Length(line_.geom) * ST_LineLocatePoint(line_.geom, points_.geom)
+           ELSE 0 END,
+       points_.place,
+       points_.message,
+       points_.date,
+       points_.time
+FROM points_
+         CROSS JOIN line_
+WHERE points_.state = 'pending'
+ORDER BY points_.ordinal
+LIMIT 1;
+$$ language sql SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.default_srid(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.default_srid(phone_ VARCHAR(64))
+    RETURNS INT
+AS
+$$
+SELECT point.srid
+FROM (SELECT route_point.srid, route_point.log_date
+      FROM hike.route_point
+               JOIN hike.route ON route_point._route_id = route._id
+               JOIN hike.hike ON route._hike_id = hike._id
+               JOIN phone.phone ON hike._phone_id = phone._id
+      WHERE phone.phone = phone_
+      UNION ALL
+      SELECT trace_point.srid, trace_point.log_date
+      FROM hike.trace_point
+               JOIN hike.trace ON trace_point._trace_id = trace._id
+               JOIN hike.hike ON trace._hike_id = hike._id
+               JOIN phone.phone ON hike._phone_id = phone._id
+      WHERE phone.phone = phone_) point
+WHERE point.srid <> 4326
+ORDER BY point.log_date DESC
+LIMIT 1;
+$$ language sql SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.route_start(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.route_start(phone_ VARCHAR(64))
+    RETURNS DATE
+AS
+$$
+SELECT MIN(route_point.date)
+FROM hike.route_point
+WHERE route_point._route_id = (SELECT route._id
+                               FROM hike.route
+                                        JOIN hike.hike ON route._hike_id = hike._id
+                                        JOIN phone.phone ON hike._phone_id = phone._id
+                               WHERE phone.phone = phone_
+                               ORDER BY route.log_date DESC
+                               LIMIT 1);
+$$ language sql SECURITY DEFINER;
+
+
+-- Hikes where the latest planned stop that should have been reached by now_, and was not skipped,
+-- is neither marked as reached nor checked in at since it was planned, with the alert level already
+-- reached for that stop. Stops planned without a time are expected at arrival_.
+DROP FUNCTION IF EXISTS public.overdue_hikes(now_ TIMESTAMP, arrival_ TIME);
+CREATE OR REPLACE FUNCTION public.overdue_hikes(now_ TIMESTAMP, arrival_ TIME)
+    RETURNS TABLE
+            (
+                phone   VARCHAR(64),
+                planned TIMESTAMP,
+                place   TEXT,
+                level   INT
+            )
+AS
+$$
+WITH route_ AS (SELECT DISTINCT ON (route._hike_id) route._id, route._hike_id
+                FROM hike.route
+                ORDER BY route._hike_id, route.log_date DESC),
+     missed_ AS (SELECT DISTINCT ON (route_._hike_id)
+                        route_._hike_id,
+                        route_point.date + COALESCE(route_point.time, arrival_) AS planned,
+                        COALESCE(route_point.place, route_point.message)        AS place,
+                        route_point.state
+                 FROM route_
+                          INNER JOIN hike.route_point ON route_point._route_id = route_._id
+                 WHERE route_point.date + COALESCE(route_point.time, arrival_) <= now_
+                   AND route_point.state <> 'skipped'
+                 ORDER BY route_._hike_id,
+                          route_point.date + COALESCE(route_point.time, arrival_) DESC),
+     checkin_ AS (SELECT trace._hike_id, MAX(trace_point.log_date)::TIMESTAMP AS checkin
+                  FROM hike.trace
+                           INNER JOIN hike.trace_point ON trace_point._trace_id = trace._id
+                  GROUP BY trace._hike_id)
+SELECT phone.phone, missed_.planned, missed_.place, COALESCE(alert.level, 0)
+FROM missed_
+         INNER JOIN hike.hike ON hike._id = missed_._hike_id
+         INNER JOIN phone.phone ON hike._phone_id = phone._id
+         LEFT JOIN checkin_ ON checkin_._hike_id = missed_._hike_id
+         LEFT JOIN hike.alert
+                   ON alert._hike_id = missed_._hike_id AND alert.planned = missed_.planned
+WHERE missed_.state = 'pending'
+  AND (checkin_.checkin IS NULL OR checkin_.checkin < missed_.planned);
+$$ language sql SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.record_alert(phone_ VARCHAR(64), planned_ TIMESTAMP, level_ INT);
+CREATE OR REPLACE FUNCTION public.record_alert(phone_ VARCHAR(64), planned_ TIMESTAMP, level_ INT)
+    RETURNS VOID
+AS
+$$
+INSERT INTO hike.alert (_hike_id, planned, level)
+SELECT hike._id, planned_, level_
+FROM hike.hike
+         INNER JOIN phone.phone ON hike._phone_id = phone._id
+WHERE phone.phone = phone_
+ON CONFLICT (_hike_id, planned) DO UPDATE SET level = EXCLUDED.level;
+$$ language sql VOLATILE
+                SECURITY DEFINER;
+
+
+DROP FUNCTION IF EXISTS public.contacts(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.contacts(phone_ VARCHAR(64))
+    RETURNS SETOF VARCHAR(64)
+AS
+$$
+SELECT contact.phone
+FROM hike.contact
+         INNER JOIN hike.hike ON contact._hike_id = hike._id
+         INNER JOIN phone.phone ON hike._phone_id = phone._id
+WHERE phone.phone = phone_
+  AND contact.active
+ORDER BY contact.log_date;
+$$ language sql SECURITY DEFINER;
+
+
+-- Reverts whichever was made last of the latest route revision and the latest checkin. The first
+-- route of a hike is kept, as there is nothing to revert it to. Returns what was undone, with the
+-- remaining number of stops for a route, or the label and time of a checkin.
+DROP FUNCTION IF EXISTS public.undo(phone_ VARCHAR(64));
+CREATE OR REPLACE FUNCTION public.undo(phone_ VARCHAR(64))
+    RETURNS TABLE
+            (
+                undone VARCHAR(16),
+                label  TEXT,
+                logged TIMESTAMPTZ,
+                stops  INT
+            )
+AS
+$$
+DECLARE
+    hike_id_     UUID := interface.phone_hike(phone_);
+    route_row_   hike.route%ROWTYPE;
+    checkin_row_ hike.trace_point%ROWTYPE;
+BEGIN
+    SELECT route.*
+    INTO route_row_
+    FROM hike.route
+    WHERE route._hike_id = hike_id_
+    ORDER BY route.log_date DESC
+    LIMIT 1;
+
+    IF (SELECT COUNT(*) FROM hike.route WHERE route._hike_id = hike_id_) < 2 THEN
+        route_row_ := NULL;
+    END IF;
+
+    SELECT trace_point.*
+    INTO checkin_row_
+    FROM hike.trace_point
+             INNER JOIN hike.trace ON trace_point._trace_id = trace._id
+    WHERE trace._hike_id = hike_id_
+      AND NOT trace_point.sos
+    ORDER BY trace_point.log_date DESC
+    LIMIT 1;
+
+    -- A checkin that marked a stop reached is logged before the revision it made.
+    IF checkin_row_._id IS NOT NULL AND
+       (route_row_._id IS NULL OR checkin_row_.log_date > route_row_.log_date OR
+        checkin_row_._route_id = route_row_._id) THEN
+        DELETE FROM hike.route WHERE route._id = checkin_row_._route_id;
+        DELETE FROM hike.trace_point WHERE trace_point._id = checkin_row_._id;
+        RETURN QUERY SELECT 'checkin'::VARCHAR(16),
+                            COALESCE(checkin_row_.place, checkin_row_.message),
+                            checkin_row_.log_date,
+                            NULL::INT;
+    ELSIF route_row_._id IS NOT NULL THEN
+        DELETE FROM hike.route WHERE route._id = route_row_._id;
+        RETURN QUERY SELECT 'route'::VARCHAR(16),
+                            NULL::TEXT,
+                            route_row_.log_date,
+                            (SELECT COUNT(*)::INT
+                             FROM hike.route_point
+                                      INNER JOIN hike.route ON route_point._route_id = route._id
+                             WHERE route._id = (SELECT route._id
+                                                FROM hike.route
+                                                WHERE route._hike_id = hike_id_
+                                                ORDER BY route.log_date DESC
+                                                LIMIT 1));
+    END IF;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Moves the planned dates and times of the pending stops still ahead of now_ by the given number of
+-- hours, in a new revision of the route. Stops planned without a time are taken to be at arrival_
+-- when telling whether they are ahead, and are only moved by the whole days of the shift.
+DROP FUNCTION IF EXISTS public.shift_route(phone_ VARCHAR(64), hours_ JSONB, now_ TIMESTAMP,
+                                           arrival_ TIME);
+CREATE OR REPLACE FUNCTION public.shift_route(phone_ VARCHAR(64), hours_ JSONB, now_ TIMESTAMP,
+                                             arrival_ TIME)
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_id_  UUID     := interface.phone_hike(phone_);
+    shift_    INTERVAL := make_interval(hours => (hours_ #>> '{}')::INT);
+    days_     INT      := (hours_ #>> '{}')::INT / 24;
+    revision_ RECORD;
+BEGIN
+    SELECT * INTO revision_ FROM interface.revise_route(hike_id_, NULL, NULL, 0);
+
+    UPDATE hike.route_point
+    SET date = CASE
+                   WHEN route_point.time IS NULL THEN route_point.date + days_
+                   ELSE (route_point.date + route_point.time + shift_)::DATE END,
+        time = (route_point.date + route_point.time + shift_)::TIME
+    WHERE route_point._route_id = revision_._route_id
+      AND route_point.state = 'pending'
+      AND route_point.date + COALESCE(route_point.time, arrival_) > now_;
+
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Marks the given stop as reached or skipped, in a new revision of the route.
+DROP FUNCTION IF EXISTS public.mark_stop(phone_ VARCHAR(64), stop_ JSONB, state_ VARCHAR(16));
+CREATE OR REPLACE FUNCTION public.mark_stop(phone_ VARCHAR(64), stop_ JSONB, state_ VARCHAR(16))
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_id_  UUID := interface.phone_hike(phone_);
+    ordinal_  INT  := (stop_ #>> '{}')::INT;
+    revision_ RECORD;
+BEGIN
+    SELECT * INTO revision_ FROM interface.revise_route(hike_id_, NULL, NULL, 0);
+
+    UPDATE hike.route_point
+    SET state = state_
+    WHERE route_point._route_id = revision_._route_id
+      AND route_point.ordinal = ordinal_;
+
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Stop does not exist in route!';
+    END IF;
+
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Marks the pending stop of the route nearest to the latest checkin as reached, if it is within
+-- radius_ metres. The checkin refers to the revision, which is undone with it.
+DROP FUNCTION IF EXISTS interface.reach_nearest_stop(hike_id_ UUID, radius_ DOUBLE PRECISION);
+CREATE OR REPLACE FUNCTION interface.reach_nearest_stop(hike_id_ UUID,
+                                                        radius_ DOUBLE PRECISION = 1000)
+    RETURNS VOID
+AS
+$$
+DECLARE
+    checkin_  hike.trace_point%ROWTYPE;
+    ordinal_  INT;
+    revision_ RECORD;
+BEGIN
+    SELECT trace_point.*
+    INTO checkin_
+    FROM hike.trace_point
+             INNER JOIN hike.trace ON trace_point._trace_id = trace._id
+    WHERE trace._hike_id = hike_id_
+    ORDER BY trace_point.log_date DESC
+    LIMIT 1;
+
+    SELECT route_point.ordinal
+    INTO ordinal_
+    FROM hike.route_point
+    WHERE route_point._route_id = (SELECT route._id
+                                   FROM hike.route
+                                   WHERE route._hike_id = hike_id_
+                                   ORDER BY route.log_date DESC
+                                   LIMIT 1)
+      AND route_point.state = 'pending'
+      AND ST_DWithin(route_point.geom, checkin_.geom, radius_)
+    ORDER BY ST_Distance(route_point.geom, checkin_.geom)
+    LIMIT 1;
+
+    IF ordinal_ IS NOT NULL THEN
+        SELECT * INTO revision_ FROM interface.revise_route(hike_id_, NULL, NULL, 0);
+        UPDATE hike.route_point
+        SET state = 'reached'
+        WHERE route_point._route_id = revision_._route_id
+          AND route_point.ordinal = ordinal_;
+        UPDATE hike.trace_point
+        SET _route_id = revision_._route_id
+        WHERE trace_point._id = checkin_._id;
+    END IF;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
+
+
+-- Sets the details of the hike that are given, keeping the others.
+DROP FUNCTION IF EXISTS public.edit_info(phone_ VARCHAR(64), info_ JSONB);
+CREATE OR REPLACE FUNCTION public.edit_info(phone_ VARCHAR(64), info_ JSONB)
+    RETURNS SETOF interface.hike
+AS
+$$
+DECLARE
+    hike_id_ UUID := interface.phone_hike(phone_);
+BEGIN
+    UPDATE hike.hike
+    SET name       = COALESCE(info_ ->> 'name', hike.name),
+        party      = COALESCE((info_ ->> 'party')::INT, hike.party),
+        tent       = COALESCE(info_ ->> 'tent', hike.tent),
+        experience = COALESCE(info_ ->> 'experience', hike.experience)
+    WHERE hike._id = hike_id_;
+
+    RETURN QUERY SELECT * FROM interface.hike WHERE hike._id = hike_id_;
+END;
+$$ language plpgsql VOLATILE
+                    SECURITY DEFINER;
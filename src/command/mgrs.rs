@@ -0,0 +1,119 @@
+// Lookups for the Military Grid Reference System, which prefixes UTM coordinates with a latitude
+// band and a letter pair identifying a 100 km square within the zone.
+
+pub const SQUARE: u32 = 100_000;
+pub const CYCLE: u32 = 2_000_000;
+
+const BANDS: &str = "CDEFGHJKLMNPQRSTUVWX";
+const ROWS: &str = "ABCDEFGHJKLMNPQRSTUV";
+const COLUMNS: [&str; 3] = ["STUVWXYZ", "ABCDEFGH", "JKLMNPQR"];
+
+const K0: f64 = 0.9996;
+const A: f64 = 6_378_137.0;
+const F: f64 = 1.0 / 298.257_222_101;
+
+pub fn column_eastings(zone: u32, column: char) -> Option<u32> {
+    let columns = COLUMNS[(zone % 3) as usize];
+    let i = columns.find(column.to_ascii_uppercase())?;
+    Some((i as u32 + 1) * SQUARE)
+}
+
+// Row letters repeat every 2000 km, so this is the northing modulo `CYCLE`.
+pub fn row_northings(zone: u32, row: char) -> Option<u32> {
+    let offset = if zone.is_multiple_of(2) { 5 } else { 0 };
+    let i = ROWS.find(row.to_ascii_uppercase())?;
+    Some(((i + ROWS.len() - offset) % ROWS.len()) as u32 * SQUARE)
+}
+
+// Only the northern hemisphere bands are supported, as are only northern UTM zones.
+pub fn band_northings(band: char) -> Option<u32> {
+    let i = BANDS.find(band.to_ascii_uppercase())?;
+    if i < BANDS.len() / 2 {
+        return None;
+    }
+
+    let latitude = (i as f64 * 8.0 - 80.0).to_radians();
+    let northings = K0 * meridian_arc(latitude);
+    Some((northings / SQUARE as f64) as u32 * SQUARE)
+}
+
+// Formats UTM coordinates as a one metre MGRS reference, e.g. `33W XR 12345 67890`.
+pub fn format(zone: u32, latitude: f64, eastings: f64, northings: f64) -> Option<String> {
+    let band = ((latitude + 80.0) / 8.0).floor() as usize;
+    // Band X is extended to 84°N.
+    let band = BANDS.chars().nth(band.min(BANDS.len() - 1))?;
+
+    let e = eastings.round() as u32;
+    let n = northings.round() as u32;
+    let column = COLUMNS[(zone % 3) as usize]
+        .chars()
+        .nth((e / SQUARE).checked_sub(1)? as usize)?;
+    let offset = if zone.is_multiple_of(2) { 5 } else { 0 };
+    let row = ROWS
+        .chars()
+        .nth(((n % CYCLE / SQUARE) as usize + offset) % ROWS.len())?;
+
+    Some(format!(
+        "{}{} {}{} {:05} {:05}",
+        zone,
+        band,
+        column,
+        row,
+        e % SQUARE,
+        n % SQUARE
+    ))
+}
+
+// Distance along the GRS80 central meridian from the equator to the given latitude.
+fn meridian_arc(latitude: f64) -> f64 {
+    let e2 = F * (2.0 - F);
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+
+    A * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * latitude
+        - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * latitude).sin()
+        + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * latitude).sin()
+        - (35.0 * e6 / 3072.0) * (6.0 * latitude).sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_squares_in_odd_zones() {
+        assert_eq!(column_eastings(33, 'X'), Some(600_000));
+        assert_eq!(row_northings(33, 'R'), Some(1_500_000));
+    }
+
+    #[test]
+    fn finds_squares_in_even_zones() {
+        assert_eq!(column_eastings(34, 'D'), Some(400_000));
+        assert_eq!(row_northings(34, 'B'), Some(1_600_000));
+        assert_eq!(row_northings(34, 'F'), Some(0));
+    }
+
+    #[test]
+    fn rejects_unknown_letters() {
+        assert_eq!(column_eastings(33, 'I'), None);
+        assert_eq!(column_eastings(34, 'S'), None);
+        assert_eq!(row_northings(33, 'W'), None);
+        assert_eq!(band_northings('I'), None);
+        assert_eq!(band_northings('C'), None);
+    }
+
+    #[test]
+    fn floors_bands_to_squares() {
+        assert_eq!(band_northings('N'), Some(0));
+        assert_eq!(band_northings('V'), Some(6_200_000));
+        assert_eq!(band_northings('W'), Some(7_000_000));
+    }
+
+    #[test]
+    fn formats_references() {
+        let reference = format(33, 68.2, 612_345.0, 7_567_890.0);
+        assert_eq!(reference.as_deref(), Some("33W XR 12345 67890"));
+        let reference = format(34, 69.1, 412_345.0, 7_667_890.0);
+        assert_eq!(reference.as_deref(), Some("34W DB 12345 67890"));
+    }
+}
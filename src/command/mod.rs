@@ -1,15 +1,49 @@
+pub mod mgrs;
 mod parser;
+mod resolve;
 
-use chrono::prelude::{NaiveDate, NaiveTime};
-use serde::{Serialize, Serializer};
+pub use parser::{help, split_marker, starts_command, suggestion, Marker};
+
+use crate::gazetteer::Gazetteer;
+use chrono::prelude::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{ser, Serialize, Serializer};
+
+// Information from outside of the message itself that is needed to parse it.
+pub struct Context<'a> {
+    pub gazetteer: &'a Gazetteer,
+    pub received: NaiveDateTime,
+}
+
+// What earlier commands tell about the hike, for the parts of a command that refer to them.
+pub struct Defaults {
+    pub projection: Option<Projection>,
+    pub start: NaiveDate,
+}
 
 #[derive(Serialize)]
 #[serde(untagged)]
 pub enum Command {
     Create(Vec<Point>),
     Edit(Vec<Point>),
-    Checkin(Point),
+    // Amendments make a new revision of the route, with points appended, inserted before the
+    // given stop, or the given stop removed.
+    Add(Vec<Point>),
+    Insert(Stop, Vec<Point>),
+    Remove(Stop),
+    // Moves the schedule of the stops still ahead by the given hours, later or earlier if negative.
+    Shift(Hours),
+    Reached(Stop),
+    Skip(Stop),
+    // Reverts the latest route revision or checkin, whichever was made last.
+    Undo,
+    Checkin(Checkin),
     Complete,
+    Contact(Contact),
+    Info(Info),
+    Sos(Sos),
+    // Asks for the state of the sender's hike, or of the hike of the given phone for contacts.
+    Status(Option<Phone>),
+    Help(Option<Topic>),
 }
 
 #[derive(Serialize)]
@@ -24,21 +58,31 @@ pub struct Point {
 #[derive(Serialize)]
 pub struct Position {
     #[serde(rename = "srid")]
-    projection: Projection,
+    projection: Option<Projection>,
     eastings: Coordinate,
     northings: Coordinate,
+    precision: Option<Precision>,
+    place: Option<String>,
 }
 
-type Coordinate = u32;
+// For WGS84 positions, eastings holds the longitude and northings the latitude.
+type Coordinate = f64;
+// Side in metres of the square a position is known to lie within.
+type Precision = u32;
 
+#[derive(Clone, Copy)]
 pub enum Projection {
     UTM32,
     UTM33,
     UTM34,
     UTM35,
+    SWEREF99TM,
+    RT90,
+    EUREF89NTM(u8),
+    WGS84,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, Copy, PartialEq)]
 pub enum Action {
     Food,
     Tent,
@@ -47,21 +91,138 @@ pub enum Action {
 
 type Message = String;
 
-pub struct Date(NaiveDate);
+// Stops are numbered along the route, from one.
+pub type Stop = u32;
+pub type Hours = i32;
+
+// Emergency contacts are added by the hiker, and must accept before they are alerted.
+#[derive(Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Contact {
+    Add { phone: Phone, name: Option<String> },
+    Remove { phone: Phone },
+    Accept,
+}
+
+// A checkin may tell that the nearest planned stop was reached.
+#[derive(Serialize)]
+pub struct Checkin {
+    point: Point,
+    reached: bool,
+}
+
+// What rescuers ask about the hike. Details not given are left as they were.
+#[derive(Serialize, Default)]
+pub struct Info {
+    name: Option<Message>,
+    party: Option<u32>,
+    tent: Option<String>,
+    experience: Option<String>,
+}
+
+// A distress call, with the position if the hiker could give it.
+#[derive(Serialize)]
+pub struct Sos {
+    point: Option<Point>,
+    message: Option<Message>,
+}
+
+// What help is asked for; a command, or how to give positions.
+#[derive(Serialize, Clone, Copy)]
+pub enum Topic {
+    Create,
+    Edit,
+    Add,
+    Insert,
+    Remove,
+    Shift,
+    Mark,
+    Undo,
+    Checkin,
+    Complete,
+    Contact,
+    Info,
+    Sos,
+    Status,
+    Coordinates,
+}
+
+// In international format, e.g. +46701234567.
+type Phone = String;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Language {
+    English,
+    Swedish,
+    Norwegian,
+}
+
+pub enum Date {
+    Absolute(NaiveDate),
+    // Day of the route, counting its first date as day one.
+    Day(u32),
+}
+
 pub struct Time(NaiveTime);
 
+impl Projection {
+    pub fn from_srid(srid: i32) -> Option<Self> {
+        use Projection::*;
+        match srid {
+            25832 => Some(UTM32),
+            25833 => Some(UTM33),
+            25834 => Some(UTM34),
+            25835 => Some(UTM35),
+            3006 => Some(SWEREF99TM),
+            3021 => Some(RT90),
+            5105..=5130 => Some(EUREF89NTM((srid - 5100) as u8)),
+            4326 => Some(WGS84),
+            _ => None,
+        }
+    }
+
+    pub fn srid(&self) -> i32 {
+        use Projection::*;
+        match self {
+            UTM32 => 25832,
+            UTM33 => 25833,
+            UTM34 => 25834,
+            UTM35 => 25835,
+            SWEREF99TM => 3006,
+            RT90 => 3021,
+            EUREF89NTM(zone) => 5100 + *zone as i32,
+            WGS84 => 4326,
+        }
+    }
+}
+
+impl Language {
+    pub fn from_code(code: &str) -> Option<Self> {
+        use Language::*;
+        match code {
+            "en" => Some(English),
+            "sv" => Some(Swedish),
+            "no" => Some(Norwegian),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        use Language::*;
+        match self {
+            English => "en",
+            Swedish => "sv",
+            Norwegian => "no",
+        }
+    }
+}
+
 impl Serialize for Projection {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        use Projection::*;
-        match self {
-            UTM32 => serializer.serialize_i32(25832),
-            UTM33 => serializer.serialize_i32(25833),
-            UTM34 => serializer.serialize_i32(25834),
-            UTM35 => serializer.serialize_i32(25835),
-        }
+        serializer.serialize_i32(self.srid())
     }
 }
 
@@ -70,8 +231,10 @@ impl Serialize for Date {
     where
         S: Serializer,
     {
-        let Date(date) = self;
-        serializer.serialize_str(&format!("{}", date)[..])
+        match self {
+            Date::Absolute(date) => serializer.serialize_str(&format!("{}", date)[..]),
+            Date::Day(_) => Err(ser::Error::custom("route day not resolved")),
+        }
     }
 }
 
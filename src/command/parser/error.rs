@@ -1,5 +1,7 @@
+use crate::command::Language::{self, *};
 use crate::error::Error;
 use nom::Err;
+use std::cmp::Ordering;
 
 pub type ParseResult<'a, O, E = ParseError<'a>> = Result<(&'a str, O), Err<E>>;
 
@@ -9,15 +11,29 @@ pub struct ParseError<'a> {
     pub error: ErrorKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ErrorKind {
     NotRecognised,
+    ParsePosition,
     ParseProjection,
     ParseCoordinate,
+    CoordinateOutOfRange,
+    MinutesOutOfRange,
+    SecondsOutOfRange,
+    InvalidBand,
+    InvalidGridSquare,
+    UnknownPlace,
+    AmbiguousPlace(Vec<String>),
     ParseNumber,
     ParseDate,
     ParseTime,
     DateOutOfRange,
+    InvalidPhone,
+    ParseStop,
+    InvalidStop,
+    ParseShift,
+    UnknownDetail,
+    InvalidDetail,
     Nom(nom::error::ErrorKind),
 }
 
@@ -25,26 +41,155 @@ impl<'a> ParseError<'a> {
     pub fn new(input: &'a str, error: ErrorKind) -> Self {
         ParseError { input, error }
     }
+
+    // Whether the message did not look like any command, in which case help is suggested.
+    pub fn suggests_help(&self) -> bool {
+        matches!(self.error, ErrorKind::NotRecognised)
+    }
+
+    // Whether the error tells no more than that the input was not understood.
+    pub fn is_vague(&self) -> bool {
+        matches!(self.error, ErrorKind::NotRecognised | ErrorKind::Nom(_))
+    }
+
+    // The word at which parsing stopped, for pointing the sender at what went wrong.
+    pub fn fragment(&self) -> &'a str {
+        let input = self.input.trim_start();
+        let end = input.find(char::is_whitespace).unwrap_or(input.len());
+        &input[..end]
+    }
 }
 
-impl<'a> Error for ParseError<'a> {
-    fn description(&self) -> String {
+impl<'a> ParseError<'a> {
+    // What went wrong, in the language the sender is replied to in.
+    pub fn localized(&self, language: Language) -> String {
         use ErrorKind::*;
 
-        let string: &'a str = match self.error {
-            NotRecognised => "Failed to parse input",
-            ParseProjection => "Failed to parse projection",
-            ParseCoordinate => "Failed to parse coordinate",
-            ParseNumber => "Failed to parse number",
-            ParseDate => "Failed to parse date",
-            ParseTime => "Failed to parse time",
-            DateOutOfRange => "Date out of range",
-            Nom(_) => panic!(),
+        let (english, swedish, norwegian) = match &self.error {
+            NotRecognised | Nom(_) => (
+                "Failed to parse input",
+                "Kunde inte tolka meddelandet",
+                "Kunne ikke tolke meldingen",
+            ),
+            ParsePosition => (
+                "Failed to parse position",
+                "Kunde inte tolka positionen",
+                "Kunne ikke tolke posisjonen",
+            ),
+            ParseProjection => (
+                "Failed to parse projection",
+                "Kunde inte tolka projektionen",
+                "Kunne ikke tolke projeksjonen",
+            ),
+            ParseCoordinate => (
+                "Failed to parse coordinate",
+                "Kunde inte tolka koordinaten",
+                "Kunne ikke tolke koordinaten",
+            ),
+            CoordinateOutOfRange => (
+                "Coordinate out of range",
+                "Koordinaten är utanför giltigt intervall",
+                "Koordinaten er utenfor gyldig område",
+            ),
+            MinutesOutOfRange => (
+                "Minutes out of range",
+                "Minuterna är utanför giltigt intervall",
+                "Minuttene er utenfor gyldig område",
+            ),
+            SecondsOutOfRange => (
+                "Seconds out of range",
+                "Sekunderna är utanför giltigt intervall",
+                "Sekundene er utenfor gyldig område",
+            ),
+            InvalidBand => (
+                "Invalid latitude band",
+                "Ogiltigt latitudband",
+                "Ugyldig breddegradsbånd",
+            ),
+            InvalidGridSquare => (
+                "Invalid 100 km grid square",
+                "Ogiltig 100 km-ruta",
+                "Ugyldig 100 km-rute",
+            ),
+            UnknownPlace => ("Unknown place", "Okänd plats", "Ukjent sted"),
+            AmbiguousPlace(candidates) => {
+                let candidates = candidates.join(", ");
+                return match language {
+                    English => format!("Ambiguous place, one of: {}", candidates),
+                    Swedish => format!("Tvetydig plats, en av: {}", candidates),
+                    Norwegian => format!("Tvetydig sted, ett av: {}", candidates),
+                };
+            }
+            ParseNumber => (
+                "Failed to parse number",
+                "Kunde inte tolka talet",
+                "Kunne ikke tolke tallet",
+            ),
+            ParseDate => (
+                "Failed to parse date",
+                "Kunde inte tolka datumet",
+                "Kunne ikke tolke datoen",
+            ),
+            ParseTime => (
+                "Failed to parse time",
+                "Kunde inte tolka tiden",
+                "Kunne ikke tolke klokkeslettet",
+            ),
+            DateOutOfRange => (
+                "Date out of range",
+                "Datumet är utanför giltigt intervall",
+                "Datoen er utenfor gyldig område",
+            ),
+            InvalidPhone => (
+                "Invalid phone number, use e.g. +46701234567",
+                "Ogiltigt telefonnummer, använd t.ex. +46701234567",
+                "Ugyldig telefonnummer, bruk f.eks. +4791234567",
+            ),
+            ParseStop => (
+                "Failed to parse stop number",
+                "Kunde inte tolka stoppnumret",
+                "Kunne ikke tolke stoppnummeret",
+            ),
+            InvalidStop => (
+                "Stops are numbered from 1",
+                "Stoppen numreras från 1",
+                "Stoppene nummereres fra 1",
+            ),
+            ParseShift => (
+                "Failed to parse shift, use e.g. 1d or 3h",
+                "Kunde inte tolka förskjutningen, använd t.ex. 1d eller 3h",
+                "Kunne ikke tolke forskyvningen, bruk f.eks. 1d eller 3h",
+            ),
+            UnknownDetail => (
+                "Unknown info, use name, party, tent or experience",
+                "Okänd info, använd namn, antal, tält eller erfarenhet",
+                "Ukjent info, bruk navn, antall, telt eller erfaring",
+            ),
+            InvalidDetail => (
+                "Missing or invalid info value",
+                "Saknat eller ogiltigt infovärde",
+                "Manglende eller ugyldig infoverdi",
+            ),
+        };
+        let string = match language {
+            English => english,
+            Swedish => swedish,
+            Norwegian => norwegian,
         };
         String::from(string)
     }
 }
 
+// The description that is logged names the parser that failed, which the sender is not told.
+impl<'a> Error for ParseError<'a> {
+    fn description(&self) -> String {
+        match &self.error {
+            ErrorKind::Nom(kind) => String::from(kind.description()),
+            _ => self.localized(English),
+        }
+    }
+}
+
 impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
     fn from_error_kind(input: &'a str, kind: nom::error::ErrorKind) -> Self {
         ParseError {
@@ -56,6 +201,16 @@ impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
     fn append(_: &'a str, _: nom::error::ErrorKind, other: Self) -> Self {
         other
     }
+
+    // Of alternatives that all failed, the one that read the furthest tells best what went wrong,
+    // or else the first that tells more than that the input was not understood.
+    fn or(self, other: Self) -> Self {
+        match self.input.len().cmp(&other.input.len()) {
+            Ordering::Less => self,
+            Ordering::Equal if !self.is_vague() => self,
+            _ => other,
+        }
+    }
 }
 
 impl<'a> From<nom::Err<ParseError<'a>>> for ParseError<'a> {
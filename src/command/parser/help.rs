@@ -0,0 +1,155 @@
+use super::keywords::{self, word};
+use super::*;
+use Language::*;
+
+// Replies are built from the keyword tables of the parser, in the language of the request.
+pub fn help(topic: Option<Topic>, language: Language) -> String {
+    let keyword = |keywords: &[(&'static str, Language)]| word(keywords, |l| *l == language);
+    let action = |action: Action| word(keywords::ACTIONS, |(a, l)| *a == action && *l == language);
+    let detail = |detail: Detail| word(keywords::DETAILS, |(d, l)| *d == detail && *l == language);
+    let help = keyword(keywords::HELP);
+    let (mum, injured, red) = match language {
+        English => ("Mum", "Injured", "red"),
+        Swedish => ("Mamma", "Skadad", "röd"),
+        Norwegian => ("Mamma", "Skadet", "rød"),
+    };
+
+    let examples = match topic {
+        None => {
+            let topics = [
+                keywords::CREATE,
+                keywords::EDIT,
+                keywords::ADD,
+                keywords::INSERT,
+                keywords::REMOVE,
+                keywords::LATE,
+                keywords::EARLY,
+                keywords::REACHED,
+                keywords::SKIP,
+                keywords::UNDO,
+                keywords::CHECKIN,
+                keywords::COMPLETE,
+                keywords::CONTACT,
+                keywords::INFO,
+                keywords::SOS,
+                keywords::STATUS,
+                keywords::COORDINATES,
+            ]
+            .iter()
+            .map(|keywords| keyword(keywords))
+            .collect::<Vec<_>>()
+            .join(", ");
+            return match language {
+                English => format!("Topics: {}. Send \"{} <topic>\".", topics, help),
+                Swedish => format!("Ämnen: {}. Skicka \"{} <ämne>\".", topics, help),
+                Norwegian => format!("Emner: {}. Send \"{} <emne>\".", topics, help),
+            };
+        }
+        Some(Topic::Create) => vec![
+            format!(
+                "{} 33W XR 1234 5678 +1d {} 33W XR 2345 6789 +2d {}",
+                keyword(keywords::CREATE),
+                action(Action::Hut),
+                action(Action::Tent)
+            ),
+            format!(
+                "{} UTM33 7545000 654000 day 1 7552000 661000 day 2 18:00",
+                keyword(keywords::CREATE)
+            ),
+        ],
+        Some(Topic::Edit) => vec![format!(
+            "{} 33W XR 1234 5678 +1d 33W XR 3456 7890 +3d {}",
+            keyword(keywords::EDIT),
+            action(Action::Food)
+        )],
+        Some(Topic::Add) => vec![format!(
+            "{} 33W XR 4567 8901 +4d {}",
+            keyword(keywords::ADD),
+            action(Action::Tent)
+        )],
+        Some(Topic::Insert) => vec![format!(
+            "{} 2 33W XR 1800 6100 +1d 18:00",
+            keyword(keywords::INSERT)
+        )],
+        Some(Topic::Remove) => vec![format!("{} 3", keyword(keywords::REMOVE))],
+        Some(Topic::Shift) => vec![
+            format!("{} 1d", keyword(keywords::LATE)),
+            format!("{} 3h", keyword(keywords::EARLY)),
+        ],
+        Some(Topic::Mark) => vec![
+            format!("{} 2", keyword(keywords::REACHED)),
+            format!("{} 3", keyword(keywords::SKIP)),
+        ],
+        Some(Topic::Checkin) => vec![
+            format!("{} 33W XR 1500 6000 \"OK\"", keyword(keywords::CHECKIN)),
+            format!(
+                "{} {} 33W XR 1800 6100",
+                keyword(keywords::CHECKIN),
+                keyword(keywords::REACHED)
+            ),
+            format!(
+                "{} 67.85 18.55 {}",
+                keyword(keywords::CHECKIN),
+                action(Action::Tent)
+            ),
+        ],
+        Some(Topic::Undo) => vec![keyword(keywords::UNDO).to_string()],
+        Some(Topic::Complete) => vec![keyword(keywords::COMPLETE).to_string()],
+        Some(Topic::Contact) => vec![
+            format!("{} +46701234567 \"{}\"", keyword(keywords::CONTACT), mum),
+            format!(
+                "{} {} +46701234567",
+                keyword(keywords::CONTACT),
+                keyword(keywords::REMOVE)
+            ),
+            format!(
+                "{} {}",
+                keyword(keywords::CONTACT),
+                keyword(keywords::ACCEPT)
+            ),
+        ],
+        Some(Topic::Info) => vec![format!(
+            "{} {} \"Kungsleden\" {} 3 {} {}",
+            keyword(keywords::INFO),
+            detail(Detail::Name),
+            detail(Detail::Party),
+            detail(Detail::Tent),
+            red
+        )],
+        Some(Topic::Sos) => vec![
+            format!(
+                "{} 33W XR 1500 6000 \"{}\"",
+                keyword(keywords::SOS),
+                injured
+            ),
+            keyword(keywords::SOS).to_string(),
+        ],
+        Some(Topic::Status) => vec![
+            keyword(keywords::STATUS).to_string(),
+            format!("{} +46701234567", keyword(keywords::STATUS)),
+        ],
+        Some(Topic::Coordinates) => vec![
+            "33W XR 1234 5678".to_string(),
+            "UTM33 7545000 654000".to_string(),
+            "SWEREF99 7545000 654000".to_string(),
+            "67.8512 18.5503".to_string(),
+            "67°51'N 18°33'E".to_string(),
+        ],
+    };
+    let heading = match language {
+        English => "Examples",
+        Swedish => "Exempel",
+        Norwegian => "Eksempler",
+    };
+    format!("{}: {}", heading, examples.join(" / "))
+}
+
+// Appended to errors for messages that were not recognised as any command.
+pub fn suggestion(language: Language) -> String {
+    let help = word(keywords::HELP, |l| *l == language);
+    match language {
+        English => format!("Send \"{}\" for examples.", help),
+        Swedish => format!("Skicka \"{}\" för exempel.", help),
+        Norwegian => format!("Send \"{}\" for eksempler.", help),
+    }
+}
@@ -0,0 +1,125 @@
+use super::*;
+use Language::*;
+
+// Keywords are matched regardless of case and diacritics, see `text::fold`.
+pub const CREATE: &[(&str, Language)] = &[
+    ("create", English),
+    ("skapa", Swedish),
+    ("opprett", Norwegian),
+];
+pub const EDIT: &[(&str, Language)] =
+    &[("edit", English), ("ändra", Swedish), ("endre", Norwegian)];
+pub const ADD: &[(&str, Language)] = &[("add", English), ("lägg", Swedish), ("legg", Norwegian)];
+pub const INSERT: &[(&str, Language)] = &[
+    ("insert", English),
+    ("infoga", Swedish),
+    ("innfør", Norwegian),
+];
+pub const UNDO: &[(&str, Language)] =
+    &[("undo", English), ("ångra", Swedish), ("angre", Norwegian)];
+pub const LATE: &[(&str, Language)] = &[("late", English), ("sen", Swedish), ("sein", Norwegian)];
+pub const EARLY: &[(&str, Language)] = &[
+    ("early", English),
+    ("tidig", Swedish),
+    ("tidlig", Norwegian),
+];
+pub const REACHED: &[(&str, Language)] = &[
+    ("reached", English),
+    ("framme", Swedish),
+    ("nådd", Norwegian),
+];
+pub const SKIP: &[(&str, Language)] = &[("skip", English), ("hoppa", Swedish), ("hopp", Norwegian)];
+pub const CHECKIN: &[(&str, Language)] = &[
+    ("checkin", English),
+    ("incheckning", Swedish),
+    ("innsjekk", Norwegian),
+];
+pub const COMPLETE: &[(&str, Language)] = &[
+    ("complete", English),
+    ("klar", Swedish),
+    ("ferdig", Norwegian),
+];
+
+pub const CONTACT: &[(&str, Language)] = &[
+    ("contact", English),
+    ("kontakt", Swedish),
+    ("kontakt", Norwegian),
+];
+pub const INFO: &[(&str, Language)] = &[("info", English), ("info", Swedish), ("info", Norwegian)];
+// `sos` and `status` are understood in every language, but the local words come first in replies.
+pub const SOS: &[(&str, Language)] = &[
+    ("sos", English),
+    ("nöd", Swedish),
+    ("nød", Norwegian),
+    ("sos", Swedish),
+    ("sos", Norwegian),
+];
+pub const STATUS: &[(&str, Language)] = &[
+    ("status", English),
+    ("where", English),
+    ("var", Swedish),
+    ("hvor", Norwegian),
+    ("status", Swedish),
+    ("status", Norwegian),
+];
+pub const REMOVE: &[(&str, Language)] = &[
+    ("remove", English),
+    ("radera", Swedish),
+    ("fjern", Norwegian),
+];
+pub const ACCEPT: &[(&str, Language)] = &[
+    ("accept", English),
+    ("godkänn", Swedish),
+    ("godta", Norwegian),
+];
+
+// The keywords that a command starts with.
+pub const COMMANDS: &[&[(&str, Language)]] = &[
+    CREATE, EDIT, ADD, INSERT, REMOVE, LATE, EARLY, REACHED, SKIP, UNDO, CHECKIN, COMPLETE,
+    CONTACT, INFO, SOS, STATUS, HELP,
+];
+
+pub const MORE: &[(&str, Language)] = &[("more", English), ("mer", Swedish), ("mer", Norwegian)];
+pub const END: &[(&str, Language)] = &[("end", English), ("slut", Swedish), ("slutt", Norwegian)];
+pub const HELP: &[(&str, Language)] =
+    &[("help", English), ("hjälp", Swedish), ("hjelp", Norwegian)];
+pub const COORDINATES: &[(&str, Language)] = &[
+    ("coords", English),
+    ("coordinates", English),
+    ("koordinater", Swedish),
+    ("koordinater", Norwegian),
+];
+
+pub const ACTIONS: &[(&str, (Action, Language))] = &[
+    ("tent", (Action::Tent, English)),
+    ("tält", (Action::Tent, Swedish)),
+    ("telt", (Action::Tent, Norwegian)),
+    ("hut", (Action::Hut, English)),
+    ("stuga", (Action::Hut, Swedish)),
+    ("hytte", (Action::Hut, Norwegian)),
+    ("food", (Action::Food, English)),
+    ("mat", (Action::Food, Swedish)),
+    ("mat", (Action::Food, Norwegian)),
+];
+
+// The keyword in the given language, for replies.
+pub fn word<T>(keywords: &[(&'static str, T)], matches: impl Fn(&T) -> bool) -> &'static str {
+    match keywords.iter().find(|(_, value)| matches(value)) {
+        Some((word, _)) => word,
+        None => keywords[0].0,
+    }
+}
+pub const DETAILS: &[(&str, (Detail, Language))] = &[
+    ("name", (Detail::Name, English)),
+    ("namn", (Detail::Name, Swedish)),
+    ("navn", (Detail::Name, Norwegian)),
+    ("party", (Detail::Party, English)),
+    ("antal", (Detail::Party, Swedish)),
+    ("antall", (Detail::Party, Norwegian)),
+    ("tent", (Detail::Tent, English)),
+    ("tält", (Detail::Tent, Swedish)),
+    ("telt", (Detail::Tent, Norwegian)),
+    ("experience", (Detail::Experience, English)),
+    ("erfarenhet", (Detail::Experience, Swedish)),
+    ("erfaring", (Detail::Experience, Norwegian)),
+];
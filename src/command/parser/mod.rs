@@ -1,16 +1,78 @@
 mod error;
+mod help;
+mod keywords;
+
+pub use help::{help, suggestion};
 
 use super::*;
-use chrono::prelude::{NaiveDate, NaiveTime};
+use crate::text;
+use chrono::prelude::{Datelike, NaiveDate, NaiveTime, Weekday};
+use chrono::Duration;
 use error::{ErrorKind, ParseError, ParseResult};
 use nom::{
-    branch, bytes::complete as bytes, character::complete as character, multi, sequence, Err,
+    branch, bytes::complete as bytes, character::complete as character, combinator, multi,
+    sequence, Err,
 };
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
+const DATE_WINDOW_DAYS: i64 = 366;
+
+// The keys of the info command.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Detail {
+    Name,
+    Party,
+    Tent,
+    Experience,
+}
+
+// Ends a part of a message split over several, telling whether more parts follow.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Marker {
+    More,
+    End,
+}
+
+// Whether the message starts with the keyword of a command, rather than continuing an itinerary
+// split over several messages.
+pub fn starts_command(input: &str) -> bool {
+    keywords::COMMANDS
+        .iter()
+        .any(|keywords| starts_with(input, keywords))
+}
+
+// Separates a trailing marker from the rest of the message. Only routes are split, so a last
+// word is taken as a marker when earlier parts are pending or the message starts a route.
+pub fn split_marker(input: &str, pending: bool) -> (&str, Option<Marker>) {
+    let starts_route = starts_with(input, keywords::CREATE) || starts_with(input, keywords::EDIT);
+    if !pending && !starts_route {
+        return (input, None);
+    }
+    let trimmed = input.trim_end();
+    let (rest, last) = match trimmed.rfind(char::is_whitespace) {
+        Some(i) => (&trimmed[..i], &trimmed[i..]),
+        None => ("", trimmed),
+    };
+    let last = text::fold(last);
+    let is = |keywords: &[(&str, Language)]| keywords.iter().any(|(k, _)| text::fold(k) == last);
+    if is(keywords::MORE) {
+        (rest.trim_end(), Some(Marker::More))
+    } else if is(keywords::END) {
+        (rest.trim_end(), Some(Marker::End))
+    } else {
+        (input, None)
+    }
+}
+
 impl<'a> Command {
-    pub fn parse(input: &'a str) -> Result<Self, ParseError> {
-        match parse_command(input) {
+    // A message may hold several commands, separated by semicolons. Replies are given in the
+    // language of the first one whose keyword tells it, if any does.
+    pub fn parse(
+        input: &'a str,
+        context: &Context,
+    ) -> Result<(Vec<Self>, Option<Language>), ParseError<'a>> {
+        match parse_commands(input, context) {
             Ok(("", command)) => Ok(command),
             Ok((input, _)) => Err(ParseError::new(input, ErrorKind::NotRecognised)),
             Err(Err::Failure(ParseError { input, error })) => Err(ParseError::new(input, error)),
@@ -22,9 +84,46 @@ impl<'a> Command {
     }
 }
 
-fn parse_command(input: &str) -> ParseResult<Command> {
+fn parse_commands<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Vec<Command>, Option<Language>)> {
+    let parse_command = |input: &'a str| parse_command(input, context);
+    let parse_separator = sequence::terminated(bytes::tag(";"), character::multispace0);
+    let parser = sequence::terminated(
+        multi::separated_nonempty_list(parse_separator, parse_command),
+        combinator::opt(bytes::tag(";")),
+    );
+    let (input, commands) = parser(input)?;
+
+    let language = commands.iter().find_map(|(_, language)| *language);
+    let commands = commands.into_iter().map(|(command, _)| command).collect();
+    Ok((input, (commands, language)))
+}
+
+fn parse_command<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
     let parser = sequence::terminated(
-        branch::alt((parse_create, parse_edit, parse_checkin, parse_complete)),
+        branch::alt((
+            |input: &'a str| parse_create(input, context),
+            |input: &'a str| parse_edit(input, context),
+            |input: &'a str| parse_add(input, context),
+            |input: &'a str| parse_insert(input, context),
+            parse_remove,
+            parse_shift,
+            parse_reached,
+            parse_skip,
+            parse_undo,
+            |input: &'a str| parse_checkin(input, context),
+            parse_complete,
+            parse_contact,
+            parse_info,
+            |input: &'a str| parse_sos(input, context),
+            parse_status,
+            parse_help,
+        )),
         character::multispace0,
     );
     let (input, command) = parser(input)?;
@@ -32,84 +131,396 @@ fn parse_command(input: &str) -> ParseResult<Command> {
     Ok((input, command))
 }
 
-fn parse_create(input: &str) -> ParseResult<Command> {
-    let tag = "create";
-    let parser = sequence::tuple((bytes::tag_no_case(tag), multi::many0(parse_point)));
-    let (input, (_, points)) = parser(input)?;
+fn parse_create<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let parse_points = |input: &'a str| parse_points(input, context, 0);
+    let parser = sequence::tuple((make_parse_command_keyword(keywords::CREATE), parse_points));
+    let (input, (language, points)) = parser(input)?;
 
-    Ok((input, Command::Create(points)))
+    Ok((input, (Command::Create(points), language)))
 }
 
-fn parse_edit(input: &str) -> ParseResult<Command> {
-    let tag = "edit";
-    let parser = sequence::tuple((bytes::tag_no_case(tag), multi::many0(parse_point)));
-    let (input, (_, points)) = parser(input)?;
+fn parse_edit<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let parse_points = |input: &'a str| parse_points(input, context, 0);
+    let parser = sequence::tuple((make_parse_command_keyword(keywords::EDIT), parse_points));
+    let (input, (language, points)) = parser(input)?;
 
-    Ok((input, Command::Edit(points)))
+    Ok((input, (Command::Edit(points), language)))
 }
 
-fn parse_checkin(input: &str) -> ParseResult<Command> {
-    let tag = "checkin";
-    let parser = sequence::tuple((bytes::tag_no_case(tag), parse_point));
-    let (input, (_, point)) = parser(input)?;
+fn parse_add<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let parse_points = |input: &'a str| parse_points(input, context, 1);
+    let parser = sequence::tuple((make_parse_command_keyword(keywords::ADD), parse_points));
+    let (input, (language, points)) = parser(input)?;
 
-    Ok((input, Command::Checkin(point)))
+    Ok((input, (Command::Add(points), language)))
 }
 
-fn parse_complete(input: &str) -> ParseResult<Command> {
-    let tag = "complete";
-    let parser = bytes::tag_no_case(tag);
-    let (input, _) = parser(input)?;
+fn parse_insert<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let parse_points = |input: &'a str| parse_points(input, context, 1);
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::INSERT),
+        make_cut(parse_stop, ErrorKind::ParseStop),
+        parse_points,
+    ));
+    let (input, (language, stop, points)) = parser(input)?;
 
-    Ok((input, Command::Complete))
+    Ok((input, (Command::Insert(stop, points), language)))
 }
 
-fn parse_point<'a>(input: &'a str) -> ParseResult<'a, Point> {
-    let time_parser = branch::alt((parse_datetime, parse_date, parse_time));
+fn parse_remove(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::REMOVE),
+        make_cut(parse_stop, ErrorKind::ParseStop),
+    ));
+    let (input, (language, stop)) = parser(input)?;
+
+    Ok((input, (Command::Remove(stop), language)))
+}
+
+fn parse_checkin<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let parse_point = |input: &'a str| parse_point(input, context);
+    let parse_reached = sequence::preceded(
+        character::multispace1,
+        make_parse_keyword(keywords::REACHED),
+    );
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::CHECKIN),
+        combinator::opt(parse_reached),
+        make_cut(parse_point, ErrorKind::ParsePosition),
+    ));
+    let (input, (language, reached, point)) = parser(input)?;
 
-    let some_action = |input: &'a str| {
-        let (input, result) = parse_action(input)?;
-        Ok((input, Some(result)))
+    let checkin = Checkin {
+        point,
+        reached: reached.is_some(),
     };
-    let some_message = |input: &'a str| {
-        let (input, result) = parse_message(input)?;
-        Ok((input, Some(result)))
+    Ok((input, (Command::Checkin(checkin), language)))
+}
+
+fn parse_info(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_keyword(keywords::INFO),
+        multi::many1(parse_detail),
+    ));
+    let (input, (_, details)) = parser(input)?;
+
+    // The keyword is the same in all languages, unlike the keys.
+    let language = Some((details[0].0).1);
+    let mut info = Info::default();
+    for ((detail, _), value) in details {
+        match detail {
+            Detail::Name => info.name = Some(value),
+            Detail::Party => info.party = value.parse().ok(),
+            Detail::Tent => info.tent = Some(value),
+            Detail::Experience => info.experience = Some(value),
+        }
+    }
+    Ok((input, (Command::Info(info), language)))
+}
+
+fn parse_shift(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parse_late = combinator::map(make_parse_command_keyword(keywords::LATE), |l| (l, 1));
+    let parse_early = combinator::map(make_parse_command_keyword(keywords::EARLY), |l| (l, -1));
+    let parser = sequence::tuple((branch::alt((parse_late, parse_early)), parse_hours));
+    let (input, ((language, sign), hours)) = parser(input)?;
+
+    Ok((input, (Command::Shift(sign * hours), language)))
+}
+
+fn parse_reached(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::REACHED),
+        make_cut(parse_stop, ErrorKind::ParseStop),
+    ));
+    let (input, (language, stop)) = parser(input)?;
+
+    Ok((input, (Command::Reached(stop), language)))
+}
+
+fn parse_skip(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::SKIP),
+        make_cut(parse_stop, ErrorKind::ParseStop),
+    ));
+    let (input, (language, stop)) = parser(input)?;
+
+    Ok((input, (Command::Skip(stop), language)))
+}
+
+fn parse_undo(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = make_parse_command_keyword(keywords::UNDO);
+    let (input, language) = parser(input)?;
+
+    Ok((input, (Command::Undo, language)))
+}
+
+fn parse_complete(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = make_parse_command_keyword(keywords::COMPLETE);
+    let (input, language) = parser(input)?;
+
+    Ok((input, (Command::Complete, language)))
+}
+
+// The keyword is the same in Swedish and Norwegian, so the language is told by the keyword that
+// follows, if any.
+fn parse_contact(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parse_add = sequence::tuple((parse_phone, combinator::opt(parse_message)));
+    let parse_remove = sequence::tuple((
+        sequence::preceded(
+            character::multispace1,
+            make_parse_command_keyword(keywords::REMOVE),
+        ),
+        parse_phone,
+    ));
+    let parse_accept = sequence::preceded(
+        character::multispace1,
+        make_parse_command_keyword(keywords::ACCEPT),
+    );
+    let parse_change = branch::alt((
+        combinator::map(parse_remove, |(language, phone)| {
+            (Contact::Remove { phone }, language)
+        }),
+        combinator::map(parse_accept, |language| (Contact::Accept, language)),
+        combinator::map(parse_add, |(phone, name)| {
+            (Contact::Add { phone, name }, None)
+        }),
+    ));
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::CONTACT),
+        make_cut(parse_change, ErrorKind::InvalidPhone),
+    ));
+    let (input, (language, (contact, following))) = parser(input)?;
+
+    Ok((input, (Command::Contact(contact), language.or(following))))
+}
+
+// A distress call must get through whatever else it says. Text after the position that is not a
+// quoted message is taken as the message, up to the next command, as is a position that could
+// not be parsed.
+fn parse_sos<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Command, Option<Language>)> {
+    let (input, language) = make_parse_command_keyword(keywords::SOS)(input)?;
+
+    let (input, mut point) = match parse_point(input, context) {
+        Ok((input, point)) => (input, Some(point)),
+        Err(_) => (input, None),
     };
-    let some_time = |input: &'a str| {
-        let (input, result) = time_parser(input)?;
-        Ok((input, Some(result)))
+    let (input, message) = match parse_message(input) {
+        Ok((rest, message)) if ends_command(rest) => (rest, Some(message)),
+        _ => {
+            let end = input.find(';').unwrap_or(input.len());
+            let text = input[..end].trim();
+            let message = Some(text.to_string()).filter(|text| !text.is_empty());
+            (&input[end..], message)
+        }
     };
-    let none_action = |input: &'a str| Ok((input, None));
-    let none_message = |input: &'a str| Ok((input, None));
-    let none_time = |input: &'a str| Ok((input, None));
-
-    let with_all = branch::permutation((parse_position, some_action, some_message, some_time));
-    let with_action_message =
-        branch::permutation((parse_position, some_action, some_message, none_time));
-    let with_action_time =
-        branch::permutation((parse_position, some_action, none_message, some_time));
-    let with_message_time =
-        branch::permutation((parse_position, none_action, some_message, some_time));
-    let with_action = branch::permutation((parse_position, some_action, none_message, none_time));
-    let with_message = branch::permutation((parse_position, none_action, some_message, none_time));
-    let with_time = branch::permutation((parse_position, none_action, none_message, some_time));
-    let with_none = branch::permutation((parse_position, none_action, none_message, none_time));
+    // A quoted message after the position is read as part of the point, but belongs to the call.
+    let message = message.or_else(|| point.as_mut().and_then(|point| point.message.take()));
 
-    let parser = branch::alt((
-        with_all,
-        with_action_message,
-        with_action_time,
-        with_message_time,
-        with_action,
-        with_message,
-        with_time,
-        with_none,
+    Ok((input, (Command::Sos(Sos { point, message }), language)))
+}
+
+// Points follow each other up to the end of the command, or of the message. Anything else is an
+// error in the point where reading stopped.
+fn parse_points<'a>(input: &'a str, context: &Context, min: usize) -> ParseResult<'a, Vec<Point>> {
+    let parse_point = make_cut(
+        |input| parse_point(input, context),
+        ErrorKind::ParsePosition,
+    );
+    let mut input = input;
+    let mut points = vec![];
+    while points.len() < min || !ends_command(input) {
+        let (rest, point) = parse_point(input)?;
+        points.push(point);
+        input = rest;
+    }
+
+    Ok((input, points))
+}
+
+fn starts_with(input: &str, keywords: &[(&str, Language)]) -> bool {
+    match parse_word(input.trim_start()) {
+        Ok((_, word)) => {
+            let word = text::fold(word);
+            keywords
+                .iter()
+                .any(|(keyword, _)| text::fold(keyword) == word)
+        }
+        Err(_) => false,
+    }
+}
+
+fn ends_command(input: &str) -> bool {
+    let input = input.trim_start();
+    input.is_empty() || input.starts_with(';')
+}
+
+fn parse_status(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::STATUS),
+        combinator::opt(parse_phone),
     ));
-    let (input, (position, action, message, datetime)) = parser(input)?;
-    let (date, time) = match datetime {
-        Some((date, time)) => (date, time),
-        None => (None, None),
+    let (input, (language, phone)) = parser(input)?;
+
+    Ok((input, (Command::Status(phone), language)))
+}
+
+fn parse_help(input: &str) -> ParseResult<'_, (Command, Option<Language>)> {
+    let parser = sequence::tuple((
+        make_parse_command_keyword(keywords::HELP),
+        combinator::opt(parse_topic),
+    ));
+    let (input, (language, topic)) = parser(input)?;
+
+    Ok((input, (Command::Help(topic), language)))
+}
+
+// Help topics are named by the command keywords, in any language.
+fn parse_topic(input: &str) -> ParseResult<'_, Topic> {
+    let topic = |keywords, topic| combinator::map(make_parse_keyword(keywords), move |_| topic);
+    let parser = sequence::preceded(
+        character::multispace1,
+        branch::alt((
+            topic(keywords::CREATE, Topic::Create),
+            topic(keywords::EDIT, Topic::Edit),
+            topic(keywords::ADD, Topic::Add),
+            topic(keywords::INSERT, Topic::Insert),
+            topic(keywords::REMOVE, Topic::Remove),
+            topic(keywords::LATE, Topic::Shift),
+            topic(keywords::EARLY, Topic::Shift),
+            topic(keywords::REACHED, Topic::Mark),
+            topic(keywords::SKIP, Topic::Mark),
+            topic(keywords::UNDO, Topic::Undo),
+            topic(keywords::CHECKIN, Topic::Checkin),
+            topic(keywords::COMPLETE, Topic::Complete),
+            topic(keywords::CONTACT, Topic::Contact),
+            topic(keywords::INFO, Topic::Info),
+            topic(keywords::SOS, Topic::Sos),
+            topic(keywords::STATUS, Topic::Status),
+            combinator::map(make_parse_keyword(keywords::COORDINATES), |_| {
+                Topic::Coordinates
+            }),
+        )),
+    );
+    let (input, topic) = parser(input)?;
+
+    Ok((input, topic))
+}
+
+// Command keywords tell the language of the message, unless they are the same in several
+// languages, as `sos` or `kontakt`.
+fn make_parse_command_keyword(
+    keywords: &'static [(&'static str, Language)],
+) -> impl Fn(&str) -> ParseResult<'_, Option<Language>> {
+    move |input: &str| {
+        let orig_input = input;
+        let (input, word) = parse_word(input)?;
+
+        let word = text::fold(word);
+        let mut languages = keywords
+            .iter()
+            .filter(|(keyword, _)| text::fold(keyword) == word)
+            .map(|(_, language)| *language);
+        match languages.next() {
+            Some(language) if languages.all(|other| other == language) => {
+                Ok((input, Some(language)))
+            }
+            Some(_) => Ok((input, None)),
+            None => Err(Err::Error(ParseError::new(
+                orig_input,
+                ErrorKind::NotRecognised,
+            ))),
+        }
+    }
+}
+
+// Once the keyword of a command has matched, what follows can only be its arguments, so an error
+// in them is final. It points at where they could not be read, as `kind` unless it tells more.
+fn make_cut<'a, O>(
+    parser: impl Fn(&'a str) -> ParseResult<'a, O>,
+    kind: ErrorKind,
+) -> impl Fn(&'a str) -> ParseResult<'a, O> {
+    move |input: &'a str| match parser(input) {
+        Err(Err::Error(err)) if err.is_vague() => {
+            Err(Err::Failure(ParseError::new(err.input, kind.clone())))
+        }
+        Err(Err::Error(err)) => Err(Err::Failure(err)),
+        result => result,
+    }
+}
+
+fn make_parse_keyword<T: Copy>(
+    keywords: &'static [(&'static str, T)],
+) -> impl Fn(&str) -> ParseResult<'_, T> {
+    move |input: &str| {
+        let orig_input = input;
+        let (input, word) = parse_word(input)?;
+
+        let word = text::fold(word);
+        match keywords
+            .iter()
+            .find(|(keyword, _)| text::fold(keyword) == word)
+        {
+            Some((_, value)) => Ok((input, *value)),
+            None => Err(Err::Error(ParseError::new(
+                orig_input,
+                ErrorKind::NotRecognised,
+            ))),
+        }
+    }
+}
+
+// The position, action, message and time of a point may come in any order, and all but the
+// position may be left out. Reading stops at the first word that none of them takes, and if the
+// position is missing by then, the error is that of whatever read the furthest.
+fn parse_point<'a>(input: &'a str, context: &Context) -> ParseResult<'a, Point> {
+    let orig_input = input;
+    let parse_position = |input: &'a str| parse_position(input, context);
+    let parse_datetime = |input: &'a str| parse_datetime(input, context);
+    let parse_date = |input: &'a str| parse_date(input, context);
+    let time_parser = branch::alt((parse_datetime, parse_date, parse_time));
+
+    let mut input = input;
+    let mut position = None;
+    let mut action = None;
+    let mut message = None;
+    let mut datetime = None;
+    let mut error: Option<ParseError> = None;
+    loop {
+        let read = read_missing(&mut position, parse_position, &mut input, &mut error)?
+            || read_missing(&mut action, parse_action, &mut input, &mut error)?
+            || read_missing(&mut message, parse_message, &mut input, &mut error)?
+            || read_missing(&mut datetime, &time_parser, &mut input, &mut error)?;
+        if !read {
+            break;
+        }
+    }
+    let position = match position {
+        Some(position) => position,
+        None => {
+            let error =
+                error.unwrap_or_else(|| ParseError::new(orig_input, ErrorKind::NotRecognised));
+            return Err(Err::Error(error));
+        }
     };
+    let (date, time) = datetime.unwrap_or((None, None));
 
     Ok((
         input,
@@ -123,56 +534,329 @@ fn parse_point<'a>(input: &'a str) -> ParseResult<'a, Point> {
     ))
 }
 
-fn parse_position(input: &str) -> ParseResult<Position> {
-    let parse_northings = make_parse_coordinate('N', 7);
-    let parse_eastings = make_parse_coordinate('E', 6);
-    let parser = branch::permutation((parse_projection, parse_northings, parse_eastings));
-    let (input, (projection, northings, eastings)) = parser(input)?;
+// Reads a part of a point that is still missing, telling whether it was there. Errors are kept,
+// the one that read the furthest, for when the point turns out to be incomplete.
+fn read_missing<'a, T>(
+    slot: &mut Option<T>,
+    parser: impl Fn(&'a str) -> ParseResult<'a, T>,
+    input: &mut &'a str,
+    error: &mut Option<ParseError<'a>>,
+) -> Result<bool, Err<ParseError<'a>>> {
+    if slot.is_some() {
+        return Ok(false);
+    }
+    match parser(input) {
+        Ok((rest, parsed)) => {
+            *slot = Some(parsed);
+            *input = rest;
+            Ok(true)
+        }
+        Err(Err::Error(err)) => {
+            *error = Some(match error.take() {
+                Some(previous) => nom::error::ParseError::or(previous, err),
+                None => err,
+            });
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn parse_position<'a>(input: &'a str, context: &Context) -> ParseResult<'a, Position> {
+    let parse_place = |input: &'a str| parse_place(input, context);
+    let parser = branch::alt((parse_geographic, parse_mgrs, parse_grid, parse_place));
+    let (input, position) = parser(input)?;
+
+    Ok((input, position))
+}
+
+// Places are looked up in the gazetteer, by quoted name or by a single bare word. Unknown names
+// are not errors, as quoted strings may just as well be messages.
+fn parse_place<'a>(input: &'a str, context: &Context) -> ParseResult<'a, Position> {
+    let orig_input = input;
+
+    let parse_word = sequence::preceded(
+        character::multispace1,
+        bytes::take_while1(|c: char| c.is_alphabetic() || c == '-'),
+    );
+    let parser = branch::alt((
+        parse_message,
+        combinator::map(parse_word, |word: &str| word.to_string()),
+    ));
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, name) = parsed?;
+
+    match context.gazetteer.lookup(&name) {
+        [] => Err(Err::Error(ParseError::new(
+            orig_input,
+            ErrorKind::UnknownPlace,
+        ))),
+        [place] => {
+            let position = Position {
+                projection: Some(Projection::WGS84),
+                eastings: place.longitude,
+                northings: place.latitude,
+                precision: None,
+                place: Some(place.name.clone()),
+            };
+            Ok((input, position))
+        }
+        places => {
+            let candidates = places.iter().map(|place| place.to_string()).collect();
+            Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::AmbiguousPlace(candidates),
+            )))
+        }
+    }
+}
+
+fn parse_geographic(input: &str) -> ParseResult<'_, Position> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parse_latitude = make_parse_degrees('N', 'S', 90.0);
+    let parse_longitude = make_parse_degrees('E', 'W', 180.0);
+    let parse_separator = branch::alt((
+        sequence::delimited(
+            character::multispace0,
+            bytes::tag(","),
+            character::multispace0,
+        ),
+        character::multispace1,
+    ));
+    let parser = sequence::tuple((parse_latitude, parse_separator, parse_longitude));
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, (latitude, _, longitude)) = parsed?;
 
     let position = Position {
-        projection,
-        northings,
-        eastings,
+        projection: Some(Projection::WGS84),
+        northings: latitude,
+        eastings: longitude,
+        precision: None,
+        place: None,
     };
     Ok((input, position))
 }
 
-fn parse_projection(input: &str) -> ParseResult<Projection> {
+// MGRS references name the south-west corner of a square whose side follows from the number of
+// digits given. The position is placed in the centre of that square.
+fn parse_mgrs(input: &str) -> ParseResult<'_, Position> {
     let orig_input = input;
     let (input, _) = character::multispace1(input)?;
 
+    let letters = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
     let parser = sequence::tuple((
-        branch::alt((bytes::tag_no_case("UTM"), bytes::tag(""))),
-        character::digit1,
+        parse_int::<u32>,
+        character::one_of(letters),
+        character::multispace0,
+        character::one_of(letters),
+        character::one_of(letters),
     ));
     let parsed = transform_parsed(parser(input), orig_input);
-    let (input, (_, digits)) = parsed?;
-
-    let projection = match digits {
-        "32" => Projection::UTM32,
-        "33" => Projection::UTM33,
-        "34" => Projection::UTM34,
-        "35" => Projection::UTM35,
-        str if str.len() == 2 => {
+    let (input, ((zone, _), band, _, column, row)) = parsed?;
+
+    let projection = match zone {
+        32 => Projection::UTM32,
+        33 => Projection::UTM33,
+        34 => Projection::UTM34,
+        35 => Projection::UTM35,
+        _ => {
             return Err(Err::Failure(ParseError::new(
-                input,
+                orig_input,
                 ErrorKind::ParseProjection,
             )))
         }
+    };
+    let band_northings = match mgrs::band_northings(band) {
+        Some(northings) => northings,
+        None => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::InvalidBand,
+            )))
+        }
+    };
+    let (column_eastings, row_northings) = match (
+        mgrs::column_eastings(zone, column),
+        mgrs::row_northings(zone, row),
+    ) {
+        (Some(eastings), Some(northings)) => (eastings, northings),
         _ => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::InvalidGridSquare,
+            )))
+        }
+    };
+
+    let parser = sequence::preceded(
+        character::multispace0,
+        branch::alt((
+            combinator::verify(
+                sequence::separated_pair(
+                    character::digit1,
+                    character::multispace1,
+                    character::digit1,
+                ),
+                |(eastings, northings): &(&str, &str)| eastings.len() == northings.len(),
+            ),
+            combinator::map(character::digit1, |digits: &str| {
+                digits.split_at(digits.len() / 2)
+            }),
+        )),
+    );
+    let parsed: ParseResult<(&str, &str)> = parser(input);
+    let (input, (e_str, n_str)) = match parsed {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::ParseCoordinate,
+            )))
+        }
+    };
+
+    if e_str.len() != n_str.len() || e_str.is_empty() || e_str.len() > 5 {
+        return Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::ParseCoordinate,
+        )));
+    }
+    let precision = 10u32.pow(5 - e_str.len() as u32);
+    let eastings = e_str.parse::<u32>().unwrap() * precision + column_eastings;
+    let mut northings = n_str.parse::<u32>().unwrap() * precision + row_northings;
+    while northings < band_northings {
+        northings += mgrs::CYCLE;
+    }
+
+    let centre = precision as Coordinate / 2.0;
+    let position = Position {
+        projection: Some(projection),
+        eastings: eastings as Coordinate + centre,
+        northings: northings as Coordinate + centre,
+        precision: Some(precision),
+        place: None,
+    };
+    Ok((input, position))
+}
+
+// Grid positions may leave out the projection, which is then resolved from the surrounding points
+// before the command is stored. A given projection comes first, so that it is not mistaken for
+// whatever follows the coordinates. Projections differ in how many digits their coordinates have,
+// which is what tells northings and eastings apart when they are given without prefixes.
+fn parse_grid(input: &str) -> ParseResult<'_, Position> {
+    let parser = branch::alt((
+        make_parse_grid(7..=7, 6..=6),
+        make_parse_grid(7..=7, 7..=7),
+        make_parse_grid(7..=7, 5..=6),
+    ));
+    let (input, position) = parser(input)?;
+
+    Ok((input, position))
+}
+
+fn make_parse_grid(
+    n_len: RangeInclusive<usize>,
+    e_len: RangeInclusive<usize>,
+) -> impl Fn(&str) -> ParseResult<'_, Position> {
+    move |input: &str| {
+        let parse_projection = combinator::verify(parse_projection, |projection| {
+            grid_digits(projection) == (n_len.clone(), e_len.clone())
+        });
+        let parse_northings = make_parse_coordinate('N', n_len.clone());
+        let parse_eastings = make_parse_coordinate('E', e_len.clone());
+
+        let parse_eastings_first = combinator::map(
+            sequence::tuple((&parse_eastings, &parse_northings)),
+            |(eastings, northings)| (northings, eastings),
+        );
+        let parser = sequence::tuple((
+            combinator::opt(parse_projection),
+            branch::alt((
+                sequence::tuple((&parse_northings, &parse_eastings)),
+                parse_eastings_first,
+            )),
+        ));
+        let (input, (projection, (northings, eastings))) = parser(input)?;
+
+        let position = Position {
+            projection,
+            northings,
+            eastings,
+            precision: None,
+            place: None,
+        };
+        Ok((input, position))
+    }
+}
+
+fn grid_digits(projection: &Projection) -> (RangeInclusive<usize>, RangeInclusive<usize>) {
+    use Projection::*;
+    match projection {
+        UTM32 | UTM33 | UTM34 | UTM35 | SWEREF99TM => (7..=7, 6..=6),
+        RT90 => (7..=7, 7..=7),
+        EUREF89NTM(_) => (7..=7, 5..=6),
+        WGS84 => (0..=0, 0..=0),
+    }
+}
+
+fn parse_projection(input: &str) -> ParseResult<'_, Projection> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parser = sequence::tuple((
+        branch::alt((
+            bytes::tag_no_case("UTM"),
+            bytes::tag_no_case("NTM"),
+            bytes::tag_no_case("SWEREF99TM"),
+            bytes::tag_no_case("SWEREF99"),
+            bytes::tag_no_case("SWEREF"),
+            bytes::tag_no_case("RT90"),
+            bytes::tag(""),
+        )),
+        character::digit0,
+    ));
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, (system, digits)) = parsed?;
+
+    let projection = match (&system.to_ascii_uppercase()[..], digits) {
+        ("UTM", "32") | ("", "32") => Projection::UTM32,
+        ("UTM", "33") | ("", "33") => Projection::UTM33,
+        ("UTM", "34") | ("", "34") => Projection::UTM34,
+        ("UTM", "35") | ("", "35") => Projection::UTM35,
+        ("SWEREF99TM", "") | ("SWEREF99", "") | ("SWEREF", "") => Projection::SWEREF99TM,
+        ("RT90", "") => Projection::RT90,
+        ("NTM", zone) => match zone.parse::<u8>() {
+            Ok(zone) if (5..=30).contains(&zone) => Projection::EUREF89NTM(zone),
+            _ => {
+                return Err(Err::Failure(ParseError::new(
+                    orig_input,
+                    ErrorKind::ParseProjection,
+                )))
+            }
+        },
+        ("", str) if str.len() != 2 => {
             return Err(Err::Error(ParseError::new(
                 orig_input,
                 ErrorKind::ParseProjection,
             )))
         }
+        _ => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::ParseProjection,
+            )))
+        }
     };
     Ok((input, projection))
 }
 
-fn make_parse_coordinate<'a>(
+// Prefixed coordinates shorter than expected are taken as truncated, and padded to full length.
+fn make_parse_coordinate(
     c_char: char,
-    c_len: usize,
-) -> impl Fn(&str) -> ParseResult<Coordinate> {
+    c_len: RangeInclusive<usize>,
+) -> impl Fn(&str) -> ParseResult<'_, Coordinate> {
     let c_str = c_char.to_string();
     move |input: &str| {
         let orig_input = input;
@@ -186,14 +870,22 @@ fn make_parse_coordinate<'a>(
         ));
         let parsed = transform_parsed(parser(input), orig_input);
         let (input, (prefix, (number, n_str), point, _)) = parsed?;
+        if parse_word_end(input).is_err() {
+            return Err(Err::Error(ParseError::new(
+                orig_input,
+                ErrorKind::ParseCoordinate,
+            )));
+        }
 
-        if n_str.len() == c_len {
-            Ok((input, number))
-        } else if prefix.len() == 1 && point.len() == 0 && n_str.len() <= c_len && n_str.len() > 2 {
-            let missing_pow = c_len - n_str.len();
+        let max_len = *c_len.end();
+        if c_len.contains(&n_str.len()) {
+            Ok((input, number as Coordinate))
+        } else if prefix.len() == 1 && point.is_empty() && n_str.len() < max_len && n_str.len() > 2
+        {
+            let missing_pow = max_len - n_str.len();
             let number = number * 10u32.pow(missing_pow as u32);
-            Ok((input, number))
-        } else if n_str.len() <= c_len && point.len() == 1 {
+            Ok((input, number as Coordinate))
+        } else if n_str.len() <= max_len && point.len() == 1 {
             Err(Err::Failure(ParseError::new(
                 orig_input,
                 ErrorKind::ParseCoordinate,
@@ -207,49 +899,323 @@ fn make_parse_coordinate<'a>(
     }
 }
 
-fn parse_action(input: &str) -> ParseResult<Action> {
+// Hemisphere letters are only accepted as suffixes, as the N/E prefixes belong to grid northings
+// and eastings. They must end the word, so that a message such as `west ridge` keeps its first
+// letter and does not move the position to another hemisphere.
+fn make_parse_degrees(
+    positive: char,
+    negative: char,
+    max: f64,
+) -> impl Fn(&str) -> ParseResult<'_, Coordinate> {
+    let pos_str = positive.to_string();
+    let neg_str = negative.to_string();
+    move |input: &str| {
+        let orig_input = input;
+
+        let parser = sequence::tuple((
+            branch::alt((bytes::tag("-"), bytes::tag(""))),
+            branch::alt((parse_decimal_degrees, parse_sexagesimal)),
+            branch::alt((
+                sequence::delimited(
+                    character::multispace0,
+                    bytes::tag_no_case(&pos_str[..]),
+                    parse_word_end,
+                ),
+                sequence::delimited(
+                    character::multispace0,
+                    bytes::tag_no_case(&neg_str[..]),
+                    parse_word_end,
+                ),
+                bytes::tag(""),
+            )),
+        ));
+        let parsed = transform_parsed(parser(input), orig_input);
+        let (input, (sign, degrees, hemisphere)) = parsed?;
+
+        if degrees > max {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::CoordinateOutOfRange,
+            )));
+        }
+
+        if sign.len() == 1 || hemisphere.eq_ignore_ascii_case(&neg_str[..]) {
+            Ok((input, -degrees))
+        } else {
+            Ok((input, degrees))
+        }
+    }
+}
+
+// Decimal degrees must contain a decimal point and at most three integer digits, which keeps
+// them apart from grid coordinates.
+fn parse_decimal_degrees(input: &str) -> ParseResult<'_, f64> {
     let orig_input = input;
-    let (input, _) = character::multispace1(input)?;
+    let (input, (degrees, d_str)) = parse_decimal(input)?;
 
-    let parser = branch::alt((
-        bytes::tag_no_case("tent"),
-        bytes::tag_no_case("hut"),
-        bytes::tag_no_case("food"),
+    match d_str.find('.') {
+        Some(i) if i <= 3 => Ok((input, degrees)),
+        _ => Err(Err::Error(ParseError::new(
+            orig_input,
+            ErrorKind::ParseCoordinate,
+        ))),
+    }
+}
+
+// Degrees are followed by a degree sign, a `d` or whitespace, and minutes by a prime or an
+// apostrophe if seconds follow. Seconds end with a double prime, a quote, as straight or curly as
+// phones make it, or two apostrophes.
+fn parse_sexagesimal<'a>(input: &'a str) -> ParseResult<'a, f64> {
+    let orig_input = input;
+
+    let parse_seconds = sequence::preceded(
+        branch::alt((bytes::tag("'"), bytes::tag("′"))),
+        branch::alt((
+            combinator::map(
+                sequence::terminated(
+                    sequence::preceded(character::multispace0, parse_decimal),
+                    branch::alt((
+                        bytes::tag("''"),
+                        bytes::tag("\""),
+                        bytes::tag("″"),
+                        bytes::tag("”"),
+                    )),
+                ),
+                Some,
+            ),
+            |input: &'a str| Ok((input, None)),
+        )),
+    );
+    let parser = sequence::tuple((
+        parse_int::<u32>,
+        branch::alt((
+            sequence::terminated(
+                branch::alt((bytes::tag("°"), bytes::tag_no_case("d"))),
+                character::multispace0,
+            ),
+            character::multispace1,
+        )),
+        parse_decimal,
+        branch::alt((parse_seconds, |input: &'a str| Ok((input, None)))),
     ));
+    let (input, ((degrees, d_str), _, (minutes, m_str), seconds)) = parser(input)?;
+
+    let int_len = |str: &str| str.find('.').unwrap_or(str.len());
+    let s_str = seconds.map_or("", |(_, s_str)| s_str);
+    if d_str.len() > 3 || int_len(m_str) > 2 || int_len(s_str) > 2 {
+        return Err(Err::Error(ParseError::new(
+            orig_input,
+            ErrorKind::ParseCoordinate,
+        )));
+    }
+
+    let seconds = seconds.map_or(0.0, |(seconds, _)| seconds);
+    if minutes >= 60.0 {
+        Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::MinutesOutOfRange,
+        )))
+    } else if seconds >= 60.0 {
+        Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::SecondsOutOfRange,
+        )))
+    } else {
+        let degrees = degrees as f64 + minutes / 60.0 + seconds / 3600.0;
+        Ok((input, degrees))
+    }
+}
+
+fn parse_action(input: &str) -> ParseResult<'_, Action> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parser = make_parse_keyword(keywords::ACTIONS);
     let parsed = transform_parsed(parser(input), orig_input);
-    let (input, action) = parsed?;
+    let (input, (action, _)) = parsed?;
 
-    let action = match action {
-        "tent" => Action::Tent,
-        "hut" => Action::Hut,
-        "food" => Action::Food,
-        _ => panic!(),
-    };
     Ok((input, action))
 }
 
-fn parse_message(input: &str) -> ParseResult<Message> {
+// Messages are quoted with straight quotes, within which they may be escaped, or with the
+// typographic quotes that phone keyboards substitute for them in English, Swedish, Norwegian and
+// German.
+fn parse_message(input: &str) -> ParseResult<'_, Message> {
     let orig_input = input;
     let (input, _) = character::multispace1(input)?;
 
-    let parser = sequence::delimited(
-        bytes::tag("\""),
-        bytes::escaped(character::none_of("\"\\"), '\\', character::one_of("\"\\")),
-        bytes::tag("\""),
+    let parse_straight = combinator::map(
+        sequence::delimited(
+            bytes::tag("\""),
+            bytes::escaped(character::none_of("\"\\"), '\\', character::one_of("\"\\")),
+            bytes::tag("\""),
+        ),
+        |message: &str| message.replace("\\\"", "\""),
     );
+    let parse_typographic = branch::alt((
+        make_parse_quoted("“", "”"),
+        make_parse_quoted("„", "“"),
+        make_parse_quoted("„", "”"),
+        make_parse_quoted("”", "”"),
+        make_parse_quoted("«", "»"),
+        make_parse_quoted("»", "«"),
+    ));
+    let parser = branch::alt((parse_straight, parse_typographic));
     let (input, message) = transform_parsed(parser(input), orig_input)?;
-    let message = message.to_string().replace("\\\"", "\"");
 
     Ok((input, message))
 }
 
-fn parse_datetime(input: &str) -> ParseResult<(Option<Date>, Option<Time>)> {
+// Details are a key followed by a quoted text or a single word, or the number of people for the
+// party.
+fn parse_detail(input: &str) -> ParseResult<'_, ((Detail, Language), String)> {
+    let (input, _) = character::multispace1(input)?;
+    let (_, _) = parse_word(input)?;
+    let (input, (detail, language)) = match make_parse_keyword(keywords::DETAILS)(input) {
+        Ok(parsed) => parsed,
+        Err(_) => {
+            return Err(Err::Failure(ParseError::new(
+                input,
+                ErrorKind::UnknownDetail,
+            )))
+        }
+    };
+
+    let parse_number = sequence::terminated(character::digit1, parse_word_end);
+    let parse_word_value =
+        bytes::take_while1(|c: char| !c.is_whitespace() && !is_quote(c) && c != ';');
+    let parsed = match detail {
+        Detail::Party => sequence::preceded(character::multispace1, parse_number)(input)
+            .map(|(rest, party)| (rest, party.to_string())),
+        _ => branch::alt((
+            parse_message,
+            combinator::map(
+                sequence::preceded(character::multispace1, parse_word_value),
+                str::to_string,
+            ),
+        ))(input),
+    };
+
+    match parsed {
+        Ok((_, value)) if detail == Detail::Party && value.parse::<u32>().unwrap_or(0) == 0 => Err(
+            Err::Failure(ParseError::new(input, ErrorKind::InvalidDetail)),
+        ),
+        Ok((rest, value)) => Ok((rest, ((detail, language), value))),
+        Err(Err::Failure(err)) => Err(Err::Failure(err)),
+        Err(_) => Err(Err::Failure(ParseError::new(
+            input,
+            ErrorKind::InvalidDetail,
+        ))),
+    }
+}
+
+// Shifts are given in whole days or hours, e.g. 1d or 3h, with t for timmar or timer.
+fn parse_hours(input: &str) -> ParseResult<'_, Hours> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parse_unit = branch::alt((
+        combinator::map(bytes::tag_no_case("d"), |_| 24),
+        combinator::map(bytes::tag_no_case("h"), |_| 1),
+        combinator::map(bytes::tag_no_case("t"), |_| 1),
+    ));
+    let parser = sequence::tuple((
+        parse_int::<Hours>,
+        sequence::terminated(parse_unit, parse_word_end),
+    ));
+    let (input, ((amount, _), unit)) = match parser(input) {
+        Ok(parsed) => parsed,
+        Err(Err::Failure(err)) => return Err(Err::Failure(err)),
+        Err(_) => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::ParseShift,
+            )))
+        }
+    };
+
+    let hours = amount as i64 * unit;
+    if hours == 0 || hours > DATE_WINDOW_DAYS * 24 {
+        return Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::DateOutOfRange,
+        )));
+    }
+    Ok((input, hours as Hours))
+}
+
+fn parse_stop(input: &str) -> ParseResult<'_, Stop> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parser = sequence::terminated(parse_int::<Stop>, parse_word_end);
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, (stop, _)) = parsed?;
+
+    if stop == 0 {
+        return Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::InvalidStop,
+        )));
+    }
+    Ok((input, stop))
+}
+
+// Phone numbers are given in international format, validated as in `phone.phone`.
+fn parse_phone(input: &str) -> ParseResult<'_, Phone> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    // Numbers without a country code are recognised, to tell the sender to add one.
+    let (rest, plus) = combinator::opt(bytes::tag("+"))(input)?;
+    let (rest, digits) = character::digit0(rest)?;
+    if plus.is_none() && digits.is_empty() {
+        return Err(Err::Error(ParseError::new(input, ErrorKind::NotRecognised)));
+    }
+    let (input, _) = match parse_word_end(rest) {
+        Ok(ok) if plus.is_some() && digits.len() >= 2 => ok,
+        _ => {
+            return Err(Err::Failure(ParseError::new(
+                orig_input,
+                ErrorKind::InvalidPhone,
+            )))
+        }
+    };
+
+    Ok((input, format!("+{}", digits)))
+}
+
+fn parse_datetime<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Option<Date>, Option<Time>)> {
+    let parse_date = |input: &'a str| parse_date(input, context);
     let parser = sequence::tuple((parse_date, parse_time));
     let (input, ((date, _), (_, time))) = parser(input)?;
     Ok((input, (date, time)))
 }
 
-fn parse_date(input: &str) -> ParseResult<(Option<Date>, Option<Time>)> {
+// Dates are accepted up to `DATE_WINDOW_DAYS` before or after the message was received.
+fn parse_date<'a>(
+    input: &'a str,
+    context: &Context,
+) -> ParseResult<'a, (Option<Date>, Option<Time>)> {
+    let orig_input = input;
+    let today = context.received.date();
+
+    let parse_relative_date = |input: &'a str| parse_relative_date(input, today);
+    let parser = branch::alt((parse_absolute_date, parse_relative_date, parse_route_day));
+    let (input, date) = parser(input)?;
+
+    match date {
+        Date::Absolute(date) if (date - today).num_days().abs() > DATE_WINDOW_DAYS => Err(
+            Err::Failure(ParseError::new(orig_input, ErrorKind::DateOutOfRange)),
+        ),
+        date => Ok((input, (Some(date), None))),
+    }
+}
+
+fn parse_absolute_date(input: &str) -> ParseResult<'_, Date> {
     let orig_input = input;
     let (input, _) = character::multispace1(input)?;
 
@@ -264,13 +1230,7 @@ fn parse_date(input: &str) -> ParseResult<(Option<Date>, Option<Time>)> {
     let (input, ((year, _), _, (month, _), _, (day, _))) = parsed?;
 
     let date = match NaiveDate::from_ymd_opt(year, month, day) {
-        Some(date) if 1970 <= year && year < 2030 => date,
-        Some(_) => {
-            return Err(Err::Failure(ParseError::new(
-                orig_input,
-                ErrorKind::DateOutOfRange,
-            )))
-        }
+        Some(date) => date,
         None => {
             return Err(Err::Failure(ParseError::new(
                 orig_input,
@@ -278,10 +1238,115 @@ fn parse_date(input: &str) -> ParseResult<(Option<Date>, Option<Time>)> {
             )))
         }
     };
-    Ok((input, (Some(Date(date)), None)))
+    Ok((input, Date::Absolute(date)))
 }
 
-fn parse_time<'a>(input: &'a str) -> ParseResult<(Option<Date>, Option<Time>)> {
+// Relative dates count from the day the message was received. Weekdays refer to the first such
+// day from then on, including the day itself.
+fn parse_relative_date(input: &str, today: NaiveDate) -> ParseResult<'_, Date> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parse_offset = sequence::delimited(
+        bytes::tag("+"),
+        parse_int::<i64>,
+        sequence::terminated(bytes::tag_no_case("d"), parse_word_end),
+    );
+    let parser = branch::alt((
+        combinator::map(parse_offset, |(days, _)| Some(days)),
+        combinator::map(parse_word, |word| relative_days(word, today)),
+    ));
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, days) = parsed?;
+
+    match days {
+        Some(days) if days > DATE_WINDOW_DAYS => Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::DateOutOfRange,
+        ))),
+        Some(days) => Ok((input, Date::Absolute(today + Duration::days(days)))),
+        None => Err(Err::Error(ParseError::new(
+            orig_input,
+            ErrorKind::ParseDate,
+        ))),
+    }
+}
+
+fn relative_days(word: &str, today: NaiveDate) -> Option<i64> {
+    use Weekday::*;
+    let weekday = match &word.to_lowercase()[..] {
+        "yesterday" => return Some(-1),
+        "today" => return Some(0),
+        "tomorrow" => return Some(1),
+        "mon" | "monday" => Mon,
+        "tue" | "tues" | "tuesday" => Tue,
+        "wed" | "wednesday" => Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Thu,
+        "fri" | "friday" => Fri,
+        "sat" | "saturday" => Sat,
+        "sun" | "sunday" => Sun,
+        _ => return None,
+    };
+    let from_today = weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday();
+    Some((from_today % 7) as i64)
+}
+
+// Route days are resolved against the first date of the route once the command is complete.
+fn parse_route_day(input: &str) -> ParseResult<'_, Date> {
+    let orig_input = input;
+    let (input, _) = character::multispace1(input)?;
+
+    let parser = sequence::tuple((
+        bytes::tag_no_case("day"),
+        character::multispace0,
+        sequence::terminated(parse_int::<u32>, parse_word_end),
+    ));
+    let parsed = transform_parsed(parser(input), orig_input);
+    let (input, (_, _, (day, _))) = parsed?;
+
+    if day == 0 || day as i64 > DATE_WINDOW_DAYS {
+        return Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::DateOutOfRange,
+        )));
+    }
+    Ok((input, Date::Day(day)))
+}
+
+fn parse_word(input: &str) -> ParseResult<'_, &str> {
+    bytes::take_while1(|c: char| c.is_alphabetic())(input)
+}
+
+fn make_parse_quoted(
+    open: &'static str,
+    close: &'static str,
+) -> impl Fn(&str) -> ParseResult<'_, Message> {
+    move |input: &str| {
+        let parser = sequence::delimited(
+            bytes::tag(open),
+            bytes::take_until(close),
+            bytes::tag(close),
+        );
+        let (input, message) = parser(input)?;
+
+        Ok((input, message.to_string()))
+    }
+}
+
+fn is_quote(c: char) -> bool {
+    "\"“”„«»".contains(c)
+}
+
+fn parse_word_end(input: &str) -> ParseResult<'_, ()> {
+    match input.chars().next() {
+        Some(c) if c.is_alphanumeric() => {
+            Err(Err::Error(ParseError::new(input, ErrorKind::NotRecognised)))
+        }
+        _ => Ok((input, ())),
+    }
+}
+
+fn parse_time<'a>(input: &'a str) -> ParseResult<'a, (Option<Date>, Option<Time>)> {
     let orig_input = input;
     let (input, _) = character::multispace1(input)?;
 
@@ -309,7 +1374,7 @@ fn parse_time<'a>(input: &'a str) -> ParseResult<(Option<Date>, Option<Time>)> {
     Ok((input, (None, Some(Time(time)))))
 }
 
-fn parse_int<T: FromStr>(input: &str) -> ParseResult<(T, &str)> {
+fn parse_int<T: FromStr>(input: &str) -> ParseResult<'_, (T, &str)> {
     let orig_input = input;
 
     let (input, digits) = character::digit1(input)?;
@@ -325,6 +1390,29 @@ fn parse_int<T: FromStr>(input: &str) -> ParseResult<(T, &str)> {
     Ok((input, (number, span)))
 }
 
+fn parse_decimal(input: &str) -> ParseResult<'_, (f64, &str)> {
+    let orig_input = input;
+
+    let parser = sequence::tuple((
+        character::digit1,
+        branch::alt((
+            combinator::recognize(sequence::pair(bytes::tag("."), character::digit1)),
+            bytes::tag(""),
+        )),
+    ));
+    let (input, _) = parser(input)?;
+    let span = &orig_input[..(orig_input.len() - input.len())];
+
+    let number = match span.parse::<f64>() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(Err::Failure(ParseError::new(
+            orig_input,
+            ErrorKind::ParseNumber,
+        ))),
+    }?;
+    Ok((input, (number, span)))
+}
+
 fn transform_parsed<'a, T>(output: ParseResult<'a, T>, orig_input: &'a str) -> ParseResult<'a, T> {
     match output {
         Ok(res) => Ok(res),
@@ -337,3 +1425,289 @@ fn transform_parsed<'a, T>(output: ParseResult<'a, T>, orig_input: &'a str) -> P
         _ => panic!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::gazetteer::Gazetteer;
+    use serde_json::{json, Value};
+
+    // Messages are taken to be received at noon on Wednesday 1 July 2020.
+    fn received() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 7, 1)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .unwrap()
+    }
+
+    // Parses a message as received from a phone, after the same normalisation as the server.
+    fn parse_received(message: &str) -> Result<Value, (String, String)> {
+        let gazetteer = Gazetteer::default();
+        let context = Context {
+            gazetteer: &gazetteer,
+            received: received(),
+        };
+        let message = text::normalise(message);
+        match Command::parse(&message, &context) {
+            Ok((commands, _)) => Ok(serde_json::to_value(&commands).unwrap()),
+            Err(err) => Err((err.description(), err.fragment().to_string())),
+        }
+    }
+
+    fn parse(message: &str) -> Value {
+        match parse_received(message) {
+            Ok(parsed) => parsed,
+            Err((description, fragment)) => panic!("{}: {} {}", message, description, fragment),
+        }
+    }
+
+    // The error a message is replied with, and the word it points at.
+    fn parse_error(message: &str) -> (String, String) {
+        match parse_received(message) {
+            Ok(parsed) => panic!("{}: parsed as {}", message, parsed),
+            Err(err) => err,
+        }
+    }
+
+    fn error(description: &str, fragment: &str) -> (String, String) {
+        (description.to_string(), fragment.to_string())
+    }
+
+    #[test]
+    fn accepts_no_break_spaces() {
+        let parsed = parse("checkin\u{a0}67.85\u{a0}18.55\u{202f}\"OK\"");
+        assert_eq!(parsed[0]["point"]["position"]["northings"], json!(67.85));
+        assert_eq!(parsed[0]["point"]["message"], json!("OK"));
+    }
+
+    #[test]
+    fn accepts_curly_quotes() {
+        let parsed = parse("checkin 67.85 18.55 “Camp by the lake”");
+        assert_eq!(parsed[0]["point"]["message"], json!("Camp by the lake"));
+    }
+
+    #[test]
+    fn accepts_low_quotes() {
+        let parsed = parse("sos „Skadad fot“");
+        assert_eq!(parsed[0]["message"], json!("Skadad fot"));
+    }
+
+    #[test]
+    fn accepts_guillemets() {
+        let parsed = parse("kontakt +4791234567 «Mamma»");
+        assert_eq!(parsed[0]["name"], json!("Mamma"));
+    }
+
+    #[test]
+    fn accepts_en_dashes() {
+        let parsed = parse("create 67.85 18.55 2020–07–03 67.9 18.6 2020–07–04");
+        assert_eq!(parsed[0][0]["date"], json!("2020-07-03"));
+        assert_eq!(parsed[0][1]["date"], json!("2020-07-04"));
+    }
+
+    #[test]
+    fn accepts_several_artefacts_in_one_message() {
+        let parsed = parse("info name “Kungsleden”;\u{a0}sos\u{a0}«Stuck – need help»");
+        assert_eq!(parsed[0]["name"], json!("Kungsleden"));
+        assert_eq!(parsed[1]["message"], json!("Stuck - need help"));
+    }
+
+    #[test]
+    fn reads_hemisphere_suffixes() {
+        let parsed = parse("checkin 67.85N 18.55W");
+        assert_eq!(parsed[0]["point"]["position"]["northings"], json!(67.85));
+        assert_eq!(parsed[0]["point"]["position"]["eastings"], json!(-18.55));
+    }
+
+    #[test]
+    fn leaves_words_after_degrees_alone() {
+        let parsed = parse("sos 67.85 18.55 west ridge");
+        assert_eq!(parsed[0]["point"]["position"]["eastings"], json!(18.55));
+        assert_eq!(parsed[0]["message"], json!("west ridge"));
+
+        let parsed = parse("sos 67.85N 18.55E east ridge");
+        assert_eq!(parsed[0]["point"]["position"]["eastings"], json!(18.55));
+        assert_eq!(parsed[0]["message"], json!("east ridge"));
+    }
+
+    #[test]
+    fn points_at_the_coordinate_that_failed() {
+        let failed = error("Failed to parse coordinate", "N753x");
+        assert_eq!(parse_error("checkin 33 N753x E668818"), failed);
+        let failed = error("Failed to parse coordinate", "66881");
+        assert_eq!(parse_error("checkin 33 7531609 66881"), failed);
+    }
+
+    #[test]
+    fn points_at_the_argument_that_failed() {
+        let failed = error("Unknown place", "Hemavn");
+        assert_eq!(
+            parse_error("create 33W XR 1234 5678 +1d Hemavn +2d"),
+            failed
+        );
+        assert_eq!(
+            parse_error("reached x"),
+            error("Failed to parse stop number", "x")
+        );
+        assert_eq!(
+            parse_error("checkin"),
+            error("Failed to parse position", "")
+        );
+    }
+
+    #[test]
+    fn points_at_the_start_of_unknown_commands() {
+        assert_eq!(
+            parse_error("hello there"),
+            error("Failed to parse input", "hello")
+        );
+    }
+
+    #[test]
+    fn describes_errors_in_the_language_of_the_sender() {
+        let unknown = ParseError::new("Hemavn", ErrorKind::UnknownPlace);
+        assert_eq!(unknown.localized(Language::Swedish), "Okänd plats");
+        assert_eq!(unknown.localized(Language::Norwegian), "Ukjent sted");
+        let ambiguous = ParseError::new("Vika", ErrorKind::AmbiguousPlace(vec!["A".into()]));
+        assert_eq!(
+            ambiguous.localized(Language::Swedish),
+            "Tvetydig plats, en av: A"
+        );
+    }
+
+    #[test]
+    fn reads_mgrs_references() {
+        let parsed = parse("checkin 33W XR 12345 67890");
+        let position = &parsed[0]["point"]["position"];
+        assert_eq!(position["srid"], json!(25833));
+        assert_eq!(position["eastings"], json!(612_345.5));
+        assert_eq!(position["northings"], json!(7_567_890.5));
+        assert_eq!(position["precision"], json!(1));
+
+        let parsed = parse("checkin 34W DB 12345 67890");
+        let position = &parsed[0]["point"]["position"];
+        assert_eq!(position["srid"], json!(25834));
+        assert_eq!(position["eastings"], json!(412_345.5));
+        assert_eq!(position["northings"], json!(7_667_890.5));
+    }
+
+    #[test]
+    fn reads_concatenated_mgrs_references() {
+        let parsed = parse("checkin 33WXR1234567890");
+        assert_eq!(parsed[0]["point"]["position"]["eastings"], json!(612_345.5));
+        let parsed = parse("checkin 33W XR 123 678");
+        let position = &parsed[0]["point"]["position"];
+        assert_eq!(position["eastings"], json!(612_350.0));
+        assert_eq!(position["northings"], json!(7_567_850.0));
+        assert_eq!(position["precision"], json!(100));
+    }
+
+    #[test]
+    fn rejects_invalid_mgrs_letters() {
+        let failed = error("Invalid latitude band", "33C");
+        assert_eq!(parse_error("checkin 33C XR 12345 67890"), failed);
+        let failed = error("Invalid 100 km grid square", "33W");
+        assert_eq!(parse_error("checkin 33W IR 12345 67890"), failed);
+    }
+
+    #[test]
+    fn reads_decimal_degrees() {
+        let parsed = parse("checkin 68.3521, 18.8312");
+        let position = &parsed[0]["point"]["position"];
+        assert_eq!(position["srid"], json!(4326));
+        assert_eq!(position["northings"], json!(68.3521));
+        assert_eq!(position["eastings"], json!(18.8312));
+    }
+
+    #[test]
+    fn rejects_degrees_out_of_range() {
+        let failed = error("Coordinate out of range", "98.35,");
+        assert_eq!(parse_error("checkin 98.35, 18.83"), failed);
+    }
+
+    #[test]
+    fn reads_degrees_minutes_and_seconds() {
+        let degrees = |parsed: &Value| {
+            let position = &parsed[0]["point"]["position"];
+            let round = |value: &Value| (value.as_f64().unwrap() * 1e4).round() / 1e4;
+            (round(&position["northings"]), round(&position["eastings"]))
+        };
+        let parsed = parse("checkin 68°21'07\"N 18°49'52\"E");
+        assert_eq!(degrees(&parsed), (68.3519, 18.8311));
+        let parsed = parse("checkin 68d21'07''N 18d49'52''E");
+        assert_eq!(degrees(&parsed), (68.3519, 18.8311));
+        let parsed = parse("checkin 68 21.12N 18 49.87E");
+        assert_eq!(degrees(&parsed), (68.352, 18.8312));
+    }
+
+    #[test]
+    fn rejects_minutes_and_seconds_out_of_range() {
+        let failed = error("Minutes out of range", "68°61'N");
+        assert_eq!(parse_error("checkin 68°61'N 18°49'E"), failed);
+        let failed = error("Seconds out of range", "68°21'60\"N");
+        assert_eq!(parse_error("checkin 68°21'60\"N 18°49'52\"E"), failed);
+    }
+
+    #[test]
+    fn reads_national_projections() {
+        let position = |message| parse(message)[0]["point"]["position"].clone();
+        let parsed = position("checkin SWEREF99 7545000 654000");
+        assert_eq!(parsed["srid"], json!(3006));
+        assert_eq!(parsed["northings"], json!(7_545_000.0));
+        assert_eq!(parsed["eastings"], json!(654_000.0));
+        let parsed = position("checkin RT90 7545000 1654000");
+        assert_eq!(parsed["srid"], json!(3021));
+        assert_eq!(parsed["eastings"], json!(1_654_000.0));
+        let parsed = position("checkin NTM17 7545000 54000");
+        assert_eq!(parsed["srid"], json!(5117));
+        assert_eq!(parsed["eastings"], json!(54_000.0));
+    }
+
+    #[test]
+    fn rejects_unknown_projections() {
+        let failed = error("Failed to parse projection", "NTM40");
+        assert_eq!(parse_error("checkin NTM40 7545000 54000"), failed);
+        let failed = error("Failed to parse projection", "UTM36");
+        assert_eq!(parse_error("checkin UTM36 7545000 654000"), failed);
+    }
+
+    #[test]
+    fn reads_relative_dates() {
+        let date = |message| parse(message)[0]["point"]["date"].clone();
+        assert_eq!(date("checkin 67.85 18.55 tomorrow"), json!("2020-07-02"));
+        assert_eq!(date("checkin 67.85 18.55 fri"), json!("2020-07-03"));
+        assert_eq!(date("checkin 67.85 18.55 Wednesday"), json!("2020-07-01"));
+        assert_eq!(date("checkin 67.85 18.55 +2d"), json!("2020-07-03"));
+    }
+
+    #[test]
+    fn reads_route_days() {
+        let gazetteer = Gazetteer::default();
+        let context = Context {
+            gazetteer: &gazetteer,
+            received: received(),
+        };
+        let message = "create 67.85 18.55 2020-07-03 67.9 18.6 day 3 68.0 18.7 day4";
+        let (mut commands, _) = Command::parse(message, &context).unwrap();
+        let defaults = Defaults {
+            projection: None,
+            start: received().date(),
+        };
+        assert!(commands[0].resolve(&defaults).is_ok());
+        let parsed = serde_json::to_value(&commands).unwrap();
+        assert_eq!(parsed[0][1]["date"], json!("2020-07-05"));
+        assert_eq!(parsed[0][2]["date"], json!("2020-07-06"));
+    }
+
+    #[test]
+    fn rejects_dates_out_of_range() {
+        let failed = error("Date out of range", "+400d");
+        assert_eq!(parse_error("checkin 67.85 18.55 +400d"), failed);
+        let failed = error("Date out of range", "2022-07-01");
+        assert_eq!(parse_error("checkin 67.85 18.55 2022-07-01"), failed);
+        let failed = error("Date out of range", "day");
+        assert_eq!(parse_error("checkin 67.85 18.55 day 0"), failed);
+        let failed = error("Failed to parse date", "2020-13-01");
+        assert_eq!(parse_error("checkin 67.85 18.55 2020-13-01"), failed);
+    }
+}
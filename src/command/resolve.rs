@@ -0,0 +1,127 @@
+use super::*;
+use crate::error::Error;
+use chrono::Duration;
+
+#[derive(Debug)]
+pub enum ResolveError {
+    MissingProjection,
+}
+
+impl Command {
+    // Whether resolving the command needs defaults from earlier commands.
+    pub fn needs_defaults(&self) -> bool {
+        let points = self.points();
+
+        let mut needs_projection = false;
+        for point in points {
+            match point.position.projection {
+                Some(Projection::WGS84) => (),
+                Some(_) => break,
+                None => {
+                    needs_projection = true;
+                    break;
+                }
+            }
+        }
+
+        let needs_start = self.own_start().is_none()
+            && points
+                .iter()
+                .any(|point| matches!(point.date, Some(Date::Day(_))));
+
+        needs_projection || needs_start
+    }
+
+    // Points given without a projection inherit it from the preceding point of the route. The
+    // first point of a route, or a checkin, falls back to the default. Route days count from the
+    // first date of the route, or the default if the command contains no dates or amends the
+    // stored route.
+    pub fn resolve(&mut self, defaults: &Defaults) -> Result<(), ResolveError> {
+        let start = self.own_start().unwrap_or(defaults.start);
+        let points = self.points_mut();
+
+        let mut previous = defaults.projection;
+        for point in points {
+            match point.position.projection {
+                Some(Projection::WGS84) => (),
+                Some(projection) => previous = Some(projection),
+                None => match previous {
+                    Some(projection) => point.position.projection = Some(projection),
+                    None => return Err(ResolveError::MissingProjection),
+                },
+            }
+
+            if let Some(Date::Day(day)) = point.date {
+                let date = start + Duration::days(day as i64 - 1);
+                point.date = Some(Date::Absolute(date));
+            }
+        }
+        Ok(())
+    }
+
+    // The first date of the route, if given by the command.
+    fn own_start(&self) -> Option<NaiveDate> {
+        use Command::*;
+        match self {
+            Add(_) | Insert(..) => None,
+            _ => route_start(self.points()),
+        }
+    }
+
+    fn points(&self) -> &[Point] {
+        use Command::*;
+        match self {
+            Create(points) | Edit(points) | Add(points) | Insert(_, points) => &points[..],
+            Checkin(super::Checkin { point, .. })
+            | Sos(super::Sos {
+                point: Some(point), ..
+            }) => std::slice::from_ref(point),
+            Remove(_) | Shift(_) | Reached(_) | Skip(_) | Undo | Complete | Contact(_)
+            | Info(_) | Sos(_) | Status(_) | Help(_) => &[],
+        }
+    }
+
+    fn points_mut(&mut self) -> &mut [Point] {
+        use Command::*;
+        match self {
+            Create(points) | Edit(points) | Add(points) | Insert(_, points) => &mut points[..],
+            Checkin(super::Checkin { point, .. })
+            | Sos(super::Sos {
+                point: Some(point), ..
+            }) => std::slice::from_mut(point),
+            Remove(_) | Shift(_) | Reached(_) | Skip(_) | Undo | Complete | Contact(_)
+            | Info(_) | Sos(_) | Status(_) | Help(_) => &mut [],
+        }
+    }
+}
+
+fn route_start(points: &[Point]) -> Option<NaiveDate> {
+    points
+        .iter()
+        .filter_map(|point| match point.date {
+            Some(Date::Absolute(date)) => Some(date),
+            _ => None,
+        })
+        .min()
+}
+
+impl ResolveError {
+    // What is missing, in the language the sender is replied to in.
+    pub fn localized(&self, language: Language) -> String {
+        use Language::*;
+        use ResolveError::*;
+
+        let string = match (self, language) {
+            (MissingProjection, English) => "No projection given for first point",
+            (MissingProjection, Swedish) => "Ingen projektion angiven för första punkten",
+            (MissingProjection, Norwegian) => "Ingen projeksjon oppgitt for første punkt",
+        };
+        String::from(string)
+    }
+}
+
+impl Error for ResolveError {
+    fn description(&self) -> String {
+        self.localized(Language::English)
+    }
+}
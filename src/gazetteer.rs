@@ -0,0 +1,49 @@
+use crate::text::fold;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+// Places are read from a CSV file with the header `name,kind,latitude,longitude`, where `kind` is
+// e.g. hut, peak, lake or trailhead and the coordinates are WGS84 decimal degrees.
+#[derive(Deserialize)]
+pub struct Place {
+    pub name: String,
+    pub kind: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Default)]
+pub struct Gazetteer {
+    places: HashMap<String, Vec<Place>>,
+}
+
+impl Gazetteer {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, csv::Error> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let mut places: HashMap<String, Vec<Place>> = HashMap::new();
+        for place in reader.deserialize() {
+            let place: Place = place?;
+            places.entry(fold(&place.name)).or_default().push(place);
+        }
+        Ok(Gazetteer { places })
+    }
+
+    pub fn lookup(&self, name: &str) -> &[Place] {
+        match self.places.get(&fold(name)) {
+            Some(places) => &places[..],
+            None => &[],
+        }
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, {:.3}N {:.3}E)",
+            self.name, self.kind, self.latitude, self.longitude
+        )
+    }
+}
@@ -1,9 +1,17 @@
 mod command;
 mod error;
+mod gazetteer;
 mod server;
+mod sms;
+mod text;
 
-const DEFAULT_SECRET: &'static str = "secret";
-const DEFAULT_CONN: &'static str = "host=localhost";
+const DEFAULT_SECRET: &str = "secret";
+const DEFAULT_CONN: &str = "host=localhost";
+const DEFAULT_GAZETTEER: &str = "gazetteer.csv";
+const DEFAULT_SMS_URL: &str = "https://api.46elks.com";
+const DEFAULT_SMS_SENDER: &str = "Fiordland";
+const DEFAULT_GRACE_HOURS: i64 = 6;
+const MAP_URL: &str = "https://fiordland.antarkt.is/map";
 
 #[tokio::main]
 async fn main() {
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// An incoming message, independent of the provider it was received through.
+pub struct Inbound {
+    pub from: String,
+    pub message: String,
+}
+
+// Each provider posts messages in its own format and expects its own response. Providers retry
+// on errors, so the response is always sent with status 200, even for malformed messages. These
+// are dropped, as the body is only parsed once it has been accepted.
+pub trait Gateway {
+    const RESPONSE: &'static str;
+    const CONTENT_TYPE: &'static str;
+
+    fn inbound(body: &[u8]) -> Option<Inbound>;
+}
+
+// 46elks posts a form with the fields `from`, `to` and `message`.
+pub struct Elks;
+
+impl Gateway for Elks {
+    const RESPONSE: &'static str = "";
+    const CONTENT_TYPE: &'static str = "text/plain";
+
+    fn inbound(body: &[u8]) -> Option<Inbound> {
+        let mut form: HashMap<String, String> = serde_urlencoded::from_bytes(body).ok()?;
+        Some(Inbound {
+            from: form.remove("from")?,
+            message: form.remove("message")?,
+        })
+    }
+}
+
+// Twilio posts a form with the fields `From`, `To` and `Body`, and expects TwiML in response.
+pub struct Twilio;
+
+impl Gateway for Twilio {
+    const RESPONSE: &'static str =
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Response></Response>";
+    const CONTENT_TYPE: &'static str = "text/xml";
+
+    fn inbound(body: &[u8]) -> Option<Inbound> {
+        let mut form: HashMap<String, String> = serde_urlencoded::from_bytes(body).ok()?;
+        Some(Inbound {
+            from: form.remove("From")?,
+            message: form.remove("Body")?,
+        })
+    }
+}
+
+// Any other provider can post `{"from": "+46...", "message": "..."}`.
+pub struct Json;
+
+#[derive(Deserialize)]
+struct JsonBody {
+    from: String,
+    message: String,
+}
+
+impl Gateway for Json {
+    const RESPONSE: &'static str = "{}";
+    const CONTENT_TYPE: &'static str = "application/json";
+
+    fn inbound(body: &[u8]) -> Option<Inbound> {
+        let body: JsonBody = serde_json::from_slice(body).ok()?;
+        Some(Inbound {
+            from: body.from,
+            message: body.message,
+        })
+    }
+}
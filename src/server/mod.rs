@@ -1,19 +1,39 @@
-use crate::command::Command;
-use crate::{DEFAULT_CONN, DEFAULT_SECRET};
+mod gateway;
+mod monitor;
+mod outgoing;
+
+use crate::command::{self, Command, Context, Defaults, Language, Projection};
+use crate::error::Error;
+use crate::gazetteer::Gazetteer;
+use crate::sms::{self, Elks as ElksSender, Log, SmsSender};
+use crate::text;
+use crate::{
+    DEFAULT_CONN, DEFAULT_GAZETTEER, DEFAULT_GRACE_HOURS, DEFAULT_SECRET, DEFAULT_SMS_SENDER,
+    DEFAULT_SMS_URL,
+};
+use chrono::prelude::{DateTime, Local, NaiveDate, NaiveDateTime};
+use chrono::Duration;
 use futures::{stream::SplitSink, FutureExt, SinkExt, StreamExt};
+use gateway::{Elks, Gateway, Inbound, Json, Twilio};
+use monitor::SystemClock;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::env;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
-use tokio_postgres::{row::Row, NoTls};
+use tokio_postgres::{error::DbError, row::Row, GenericClient, NoTls};
 use uuid::Uuid;
+use warp::hyper::body::Bytes;
 use warp::{
+    reply,
     ws::{Message, WebSocket},
-    Filter,
+    Filter, Reply,
 };
 
+// How long after a route, or a part ending with a marker, later parts are joined with it.
+const CONTINUATION_MINUTES: i32 = 15;
+
 pub async fn serve() {
     let conn_string = match env::args().nth(1) {
         Some(conn_string) => conn_string,
@@ -25,18 +45,62 @@ pub async fn serve() {
         None => DEFAULT_SECRET.to_string(),
     };
 
-    let db = match tokio_postgres::connect(&conn_string[..], NoTls).await {
-        Ok((db, connection)) => {
-            tokio::spawn(async move {
-                if let Err(e) = connection.await {
-                    eprintln!("Database connection error: {}", e);
-                }
-            });
-            Arc::new(db)
+    let gazetteer_path = match env::args().nth(3) {
+        Some(gazetteer_path) => gazetteer_path,
+        None => DEFAULT_GAZETTEER.to_string(),
+    };
+
+    let sms_url = match env::args().nth(4) {
+        Some(sms_url) => sms_url,
+        None => DEFAULT_SMS_URL.to_string(),
+    };
+
+    // Without credentials, outgoing messages are only logged.
+    let sms_credentials = match (env::args().nth(5), env::args().nth(6)) {
+        (Some(username), Some(password)) => Some((username, password)),
+        _ => None,
+    };
+
+    let sms_sender_id = match env::args().nth(7) {
+        Some(sms_sender_id) => sms_sender_id,
+        None => DEFAULT_SMS_SENDER.to_string(),
+    };
+
+    // Hours after a missed stop before the hiker, and after twice that their contacts, are alerted.
+    let grace = match env::args().nth(8).and_then(|grace| grace.parse().ok()) {
+        Some(grace) => Duration::hours(grace),
+        None => Duration::hours(DEFAULT_GRACE_HOURS),
+    };
+
+    // Distress calls are also sent to this number, if given.
+    let rescue = env::args().nth(9);
+
+    let gazetteer = match Gazetteer::load(&gazetteer_path) {
+        Ok(gazetteer) => Arc::new(gazetteer),
+        Err(e) => {
+            eprintln!("Could not load gazetteer: {}", e);
+            Arc::new(Gazetteer::default())
         }
-        Err(_) => panic!("Could not connect to database."),
     };
 
+    let db = Arc::new(connect(&conn_string).await);
+    // Commands are applied in transactions, which need a connection of their own.
+    let commands_db = Arc::new(Mutex::new(connect(&conn_string).await));
+
+    let sms_out: Arc<dyn SmsSender> = match sms_credentials {
+        Some((username, password)) => {
+            Arc::new(ElksSender::new(sms_url, username, password, sms_sender_id))
+        }
+        None => Arc::new(Log),
+    };
+
+    tokio::spawn(monitor::monitor(
+        db.clone(),
+        sms_out.clone(),
+        Arc::new(SystemClock),
+        grace,
+    ));
+
     let (ws_tx, mut ws_rx) = mpsc::channel::<(String, SplitSink<WebSocket, Message>)>(32);
     let (db_tx, mut db_rx) = mpsc::channel::<(String, String)>(32);
     let sockets: HashMap<String, Vec<SplitSink<WebSocket, Message>>> = HashMap::new();
@@ -49,8 +113,8 @@ pub async fn serve() {
                 .query("SELECT * FROM public.hike($1)", &[&phone])
                 .await
             {
-                Ok(rows) if rows.len() > 0 => {
-                    let json = convert_rows(rows);
+                Ok(rows) if !rows.is_empty() => {
+                    let json = convert_rows(rows, "hike");
                     let _ = ws.send(Message::text(json)).await;
 
                     let mut sockets = sockets_in.lock().await;
@@ -72,9 +136,8 @@ pub async fn serve() {
             let mut sockets = sockets_out.lock().await;
             if let Some(wss) = (*sockets).get_mut(&phone) {
                 let mut remove = vec![];
-                for i in 0..wss.len() {
-                    let ws = &mut wss[i];
-                    if let Err(_) = ws.send(Message::text(json.clone())).await {
+                for (i, ws) in wss.iter_mut().enumerate() {
+                    if ws.send(Message::text(json.clone())).await.is_err() {
                         remove.push(i);
                     }
                 }
@@ -91,59 +154,41 @@ pub async fn serve() {
 
     let static_content = warp::path("static").and(warp::fs::dir("www/static"));
 
-    // We always need to return 200, due to 46Elks error handling.
-    let sms = warp::post()
+    let receiver = Receiver {
+        secret: set_secret,
+        db,
+        commands_db,
+        db_tx,
+        gazetteer,
+        sms_out,
+        rescue,
+    };
+
+    let elks_receiver = receiver.clone();
+    let elks = warp::post()
         .and(warp::path!("sms" / String))
         .and(warp::body::content_length_limit(1024 * 16))
-        .and(warp::body::form())
-        .map(move |given_secret: String, sms: HashMap<String, String>| {
-            if set_secret != given_secret {
-                return "".to_string();
-            }
+        .and(warp::body::bytes())
+        .map(move |given_secret: String, body: Bytes| {
+            elks_receiver.receive::<Elks>(given_secret, &body)
+        });
 
-            let message = match sms.get("message") {
-                Some(message) => message.clone(),
-                None => return "".to_string(),
-            };
-            let from = match sms.get("from") {
-                Some(from) => from.clone(),
-                None => return "".to_string(),
-            };
-            let command = match Command::parse(&message[..]) {
-                Ok(command) => command,
-                Err(_) => return "".to_string(),
-            };
-            let json = serde_json::to_value(&command).unwrap();
-            println!("{}", json);
-
-            use Command::*;
-            let db = db.clone();
-            let mut db_tx = db_tx.clone();
-            let query = match command {
-                Create(_) => "SELECT * FROM public.create_hike($1, $2)",
-                Edit(_) => "SELECT * FROM public.edit_route($1, $2)",
-                Checkin(_) => "SELECT * FROM public.checkin_trace($1, $2)",
-                Complete => "SELECT * FROM public.complete_hike($1)",
-            };
-            tokio::spawn(async move {
-                let rows = if let Complete = command {
-                    db.query(query, &[&from]).await.unwrap();
-                    None
-                } else {
-                    match db.query(query, &[&from, &json]).await {
-                        Ok(rows) if rows.len() > 0 => Some(rows),
-                        _ => None,
-                    }
-                };
+    let twilio_receiver = receiver.clone();
+    let twilio = warp::post()
+        .and(warp::path!("sms" / "twilio" / String))
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::bytes())
+        .map(move |given_secret: String, body: Bytes| {
+            twilio_receiver.receive::<Twilio>(given_secret, &body)
+        });
 
-                if let Some(rows) = rows {
-                    let json = convert_rows(rows);
-                    db_tx.send((from, json)).await.unwrap();
-                } else {
-                    db_tx.send((from, "null".to_string())).await.unwrap();
-                }
-            });
-            "".to_string()
+    let json_receiver = receiver.clone();
+    let json = warp::post()
+        .and(warp::path!("sms" / "json" / String))
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::bytes())
+        .map(move |given_secret: String, body: Bytes| {
+            json_receiver.receive::<Json>(given_secret, &body)
         });
 
     let ws = warp::path("listen")
@@ -158,11 +203,632 @@ pub async fn serve() {
         .and(warp::path::end())
         .and(warp::fs::file("www/static/html/map.html"));
 
-    let routes = root.or(static_content).or(sms).or(ws).or(map);
+    let routes = root
+        .or(static_content)
+        .or(twilio)
+        .or(json)
+        .or(elks)
+        .or(ws)
+        .or(map);
 
     warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
 }
 
+#[derive(Clone)]
+struct Receiver {
+    secret: String,
+    db: Arc<tokio_postgres::Client>,
+    commands_db: Arc<Mutex<tokio_postgres::Client>>,
+    db_tx: mpsc::Sender<(String, String)>,
+    gazetteer: Arc<Gazetteer>,
+    sms_out: Arc<dyn SmsSender>,
+    rescue: Option<String>,
+}
+
+impl Receiver {
+    fn receive<G: Gateway>(&self, given_secret: String, body: &[u8]) -> impl Reply {
+        if self.secret == given_secret {
+            if let Some(inbound) = G::inbound(body) {
+                self.handle(inbound);
+            }
+        }
+        reply::with_header(G::RESPONSE, "content-type", G::CONTENT_TYPE)
+    }
+
+    fn handle(&self, inbound: Inbound) {
+        let receiver = self.clone();
+        tokio::spawn(async move { receiver.process(inbound).await });
+    }
+
+    async fn process(self, inbound: Inbound) {
+        let Receiver {
+            db,
+            commands_db,
+            mut db_tx,
+            gazetteer,
+            sms_out,
+            rescue,
+            ..
+        } = self;
+        let Inbound { from, message } = inbound;
+        let message = text::normalise(&message);
+        let received = Local::now().naive_local();
+        let context = Context {
+            gazetteer: &gazetteer,
+            received,
+        };
+
+        // Itineraries may be split over several messages. Parts ending with a marker are kept until
+        // the last one arrives, and parts that make no sense on their own shortly after a route
+        // are taken to continue it.
+        let pending = match db
+            .query_opt(
+                "SELECT * FROM public.pending_message($1, $2)",
+                &[&from, &CONTINUATION_MINUTES],
+            )
+            .await
+        {
+            Ok(row) => row.map(|row| (row.get::<_, String>(0), row.get::<_, bool>(1))),
+            Err(_) => None,
+        };
+        let Joined {
+            message,
+            marker,
+            previous,
+            standalone,
+        } = join(&message, pending);
+        if let Some(command::Marker::More) = marker {
+            set_pending(&db, &from, Some((&message, false))).await;
+            let language = stored_language(&db, &from).await;
+            send_sms(db, sms_out, from, outgoing::awaiting_more(language)).await;
+            return;
+        }
+        let continued = previous.map(|previous| format!("{} {}", previous, message));
+
+        let parsed = match Command::parse(&message, &context) {
+            Ok(parsed) => Ok((parsed, &message)),
+            Err(err) => match continued
+                .as_ref()
+                .map(|continued| (continued, Command::parse(continued, &context)))
+            {
+                Some((continued, Ok((commands, language)))) if continues(&commands) => {
+                    let commands = commands
+                        .into_iter()
+                        .map(|command| match command {
+                            Command::Create(points) => Command::Edit(points),
+                            command => command,
+                        })
+                        .collect();
+                    Ok(((commands, language), continued))
+                }
+                _ => Err(err),
+            },
+        };
+        let ((commands, language), applied) = match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                eprintln!("Could not parse message: {}", err.description());
+                let language = stored_language(&db, &from).await;
+                let description = err.localized(language);
+                let mut reply = outgoing::not_parsed(&description, err.fragment(), language);
+                if err.suggests_help() {
+                    reply = format!("{} {}", reply, command::suggestion(language));
+                }
+                if !standalone {
+                    set_pending(&db, &from, None).await;
+                }
+                tokio::spawn(send_sms(db.clone(), sms_out.clone(), from.clone(), reply));
+                // A distress call must get through even if the rest of the message is garbled.
+                for mut command in distress_calls(&message, &context) {
+                    let _ = resolve(&*db, &from, &mut command, received, language).await;
+                    sos(&db, &sms_out, &mut db_tx, &from, &rescue, command).await;
+                }
+                return;
+            }
+        };
+        if continues(&commands) {
+            set_pending(&db, &from, Some((&applied[..], true))).await;
+        } else if !standalone {
+            set_pending(&db, &from, None).await;
+        }
+
+        // Keywords shared by several languages tell nothing of the language of the hiker, who is
+        // then replied to in the one stored.
+        let language = match language {
+            Some(language) => {
+                let _ = db
+                    .query(
+                        "SELECT public.set_language($1, $2)",
+                        &[&from, &language.code()],
+                    )
+                    .await;
+                language
+            }
+            None => stored_language(&db, &from).await,
+        };
+
+        // The commands of a message are applied in one transaction, so that either all or none
+        // of them are. Distress calls are set apart beforehand and handled once it has ended,
+        // whatever the outcome.
+        let count = commands.len();
+        let (distress, commands): (Vec<_>, Vec<_>) = commands
+            .into_iter()
+            .enumerate()
+            .partition(|(_, command)| matches!(command, Command::Sos(_)));
+        let mut commands_db = commands_db.lock().await;
+        let tx = match commands_db.transaction().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                eprintln!("Could not begin transaction: {}", e);
+                return;
+            }
+        };
+        let mut replies = vec![];
+        let mut changed = false;
+        let mut failed = None;
+        let mut rejected = None;
+        for (i, mut command) in commands {
+            if let Err(err) = resolve(&tx, &from, &mut command, received, language).await {
+                eprintln!("Could not resolve message: {}", err);
+                failed = Some(i + 1);
+                rejected = Some(err);
+                break;
+            }
+            match apply(&tx, &from, command, language, received).await {
+                Ok(applied) => {
+                    replies.extend(applied.replies);
+                    changed |= applied.changed;
+                }
+                Err(e) => {
+                    eprintln!("Could not apply command: {}", e);
+                    failed = Some(i + 1);
+                    rejected = std::error::Error::source(&e)
+                        .and_then(|e| e.downcast_ref::<DbError>())
+                        .and_then(|e| outgoing::rejected(e.message(), language));
+                    break;
+                }
+            }
+        }
+        let committed = match failed {
+            Some(_) => {
+                let _ = tx.rollback().await;
+                false
+            }
+            None => match tx.commit().await {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Could not commit commands: {}", e);
+                    false
+                }
+            },
+        };
+        drop(commands_db);
+
+        if committed {
+            for (to, reply) in replies {
+                tokio::spawn(send_sms(db.clone(), sms_out.clone(), to, reply));
+            }
+            if changed {
+                let json = match db.query("SELECT * FROM public.hike($1)", &[&from]).await {
+                    Ok(rows) if !rows.is_empty() => convert_rows(rows, "hike"),
+                    _ => "null".to_string(),
+                };
+                db_tx.send((from.clone(), json)).await.unwrap();
+            }
+        } else {
+            // Commands that could not be resolved, or that the database rejected for a reason the
+            // hiker can do something about, are told about like those that could not be parsed.
+            let reply = match (rejected, count) {
+                (Some(err), 1) => format!("{}.", err),
+                (Some(err), _) => {
+                    let not_applied = outgoing::not_applied(failed, count, language);
+                    format!("{} {}.", not_applied, err)
+                }
+                (None, _) => outgoing::not_applied(failed, count, language),
+            };
+            tokio::spawn(send_sms(db.clone(), sms_out.clone(), from.clone(), reply));
+        }
+
+        for (_, mut command) in distress {
+            let _ = resolve(&*db, &from, &mut command, received, language).await;
+            sos(&db, &sms_out, &mut db_tx, &from, &rescue, command).await;
+        }
+    }
+}
+
+// The language last used by the phone, for messages that are not replies to a command.
+async fn stored_language(db: &tokio_postgres::Client, phone: &str) -> Language {
+    let query = "SELECT public.language($1)";
+    let language = match db.query_opt(query, &[&phone]).await {
+        Ok(Some(row)) => row
+            .get::<_, Option<String>>(0)
+            .and_then(|code| Language::from_code(&code)),
+        _ => None,
+    };
+    language.unwrap_or(Language::English)
+}
+
+// A message read against the parts of an itinerary pending from earlier messages of the phone.
+struct Joined {
+    // The message after any parts buffered until the last one arrived.
+    message: String,
+    marker: Option<command::Marker>,
+    // A route already applied that the message may continue, if it makes no sense on its own.
+    previous: Option<String>,
+    // Whether the message starts with a command, and leaves what is pending alone.
+    standalone: bool,
+}
+
+// Messages starting with a command are never joined with pending parts, so that e.g. a distress
+// call sent while an itinerary is being written is handled at once rather than folded into it.
+fn join(message: &str, pending: Option<(String, bool)>) -> Joined {
+    let standalone = command::starts_command(message);
+    let (buffered, previous) = match pending {
+        _ if standalone => (None, None),
+        Some((pending, false)) => (Some(pending), None),
+        Some((pending, true)) => (None, Some(pending)),
+        None => (None, None),
+    };
+    let (text, marker) = command::split_marker(message, buffered.is_some());
+    let message = match buffered {
+        Some(buffered) => format!("{} {}", buffered, text),
+        None => text.to_string(),
+    };
+    Joined {
+        message,
+        marker,
+        previous,
+        standalone,
+    }
+}
+
+// The distress calls among the parts of a message that could not be parsed as a whole.
+fn distress_calls(message: &str, context: &Context) -> Vec<Command> {
+    message
+        .split(';')
+        .filter_map(|part| Command::parse(part.trim(), context).ok())
+        .flat_map(|(commands, _)| commands)
+        .filter(|command| matches!(command, Command::Sos(_)))
+        .collect()
+}
+
+// Whether later messages may continue the route given by the commands.
+fn continues(commands: &[Command]) -> bool {
+    matches!(commands, [Command::Create(_)] | [Command::Edit(_)])
+}
+
+// Keeps a message from the phone for joining with the next, or forgets it.
+async fn set_pending(db: &tokio_postgres::Client, phone: &str, pending: Option<(&str, bool)>) {
+    let result = match pending {
+        Some((message, applied)) => {
+            let query = "SELECT public.set_pending_message($1, $2, $3)";
+            db.query(query, &[&phone, &message, &applied]).await
+        }
+        None => {
+            let query = "SELECT public.clear_pending_message($1)";
+            db.query(query, &[&phone]).await
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Could not keep pending message: {}", e);
+    }
+}
+
+// What is left to do for an applied command once its transaction is committed.
+struct Applied {
+    replies: Vec<(String, String)>,
+    changed: bool,
+}
+
+// Fills in what the command leaves out from what earlier commands tell about the hike.
+async fn resolve<C: GenericClient + Sync>(
+    db: &C,
+    phone: &str,
+    command: &mut Command,
+    received: NaiveDateTime,
+    language: Language,
+) -> Result<(), String> {
+    let mut defaults = Defaults {
+        projection: None,
+        start: received.date(),
+    };
+    if command.needs_defaults() {
+        let query = "SELECT public.default_srid($1), public.route_start($1)";
+        if let Ok(Some(row)) = db.query_opt(query, &[&phone]).await {
+            let srid: Option<i32> = row.get(0);
+            let start: Option<NaiveDate> = row.get(1);
+            defaults.projection = srid.and_then(Projection::from_srid);
+            defaults.start = start.unwrap_or(defaults.start);
+        }
+    }
+    command
+        .resolve(&defaults)
+        .map_err(|err| err.localized(language))
+}
+
+async fn apply<C: GenericClient + Sync>(
+    db: &C,
+    from: &str,
+    command: Command,
+    language: Language,
+    received: NaiveDateTime,
+) -> Result<Applied, tokio_postgres::Error> {
+    use Command::*;
+    let query = match command {
+        Create(_) => "SELECT * FROM public.create_hike($1, $2)",
+        Edit(_) => "SELECT * FROM public.edit_route($1, $2)",
+        Add(_) => "SELECT * FROM public.add_route($1, $2)",
+        Insert(..) => "SELECT * FROM public.insert_route($1, $2)",
+        Remove(_) => "SELECT * FROM public.remove_route($1, $2)",
+        Shift(_) => "SELECT * FROM public.shift_route($1, $2, $3, $4)",
+        Reached(_) => "SELECT * FROM public.mark_stop($1, $2, 'reached')",
+        Skip(_) => "SELECT * FROM public.mark_stop($1, $2, 'skipped')",
+        Undo => "SELECT * FROM public.undo($1)",
+        Checkin(_) => "SELECT * FROM public.checkin_trace($1, $2)",
+        Complete => "SELECT * FROM public.complete_hike($1)",
+        Contact(_) => "SELECT * FROM public.edit_contact($1, $2)",
+        Info(_) => "SELECT * FROM public.edit_info($1, $2)",
+        Sos(_) | Status(_) | Help(_) => "",
+    };
+    let json = serde_json::to_value(&command).unwrap_or(Value::Null);
+    println!("{}", json);
+
+    let mut replies = vec![];
+    let rows = match command {
+        Complete => {
+            db.query(query, &[&from]).await?;
+            return Ok(Applied {
+                replies,
+                changed: true,
+            });
+        }
+        Contact(contact) => {
+            let rows = db.query(query, &[&from, &json]).await?;
+            if let Some(row) = rows.first() {
+                replies.extend(outgoing::contact_replies(
+                    from,
+                    &contact,
+                    row.get(2),
+                    language,
+                ));
+            }
+            // Hikers are told in their own language when a contact accepts.
+            if let command::Contact::Accept = contact {
+                for row in rows {
+                    let language = Language::from_code(row.get(1)).unwrap_or(Language::English);
+                    replies.push((row.get(0), outgoing::contact_accepted(from, language)));
+                }
+            }
+            return Ok(Applied {
+                replies,
+                changed: false,
+            });
+        }
+        Undo => {
+            let row = db.query_opt(query, &[&from]).await?;
+            let undone = row.map(|row| outgoing::Undone {
+                undone: row.get(0),
+                label: row.get(1),
+                logged: row.get::<_, DateTime<Local>>(2).naive_local(),
+                stops: row.get(3),
+            });
+            let reply = outgoing::undo_reply(undone, language);
+            replies.push((from.to_string(), reply));
+            return Ok(Applied {
+                replies,
+                changed: true,
+            });
+        }
+        Help(topic) => {
+            replies.push((from.to_string(), command::help(topic, language)));
+            return Ok(Applied {
+                replies,
+                changed: false,
+            });
+        }
+        Status(phone) => {
+            let phone = phone.unwrap_or_else(|| from.to_string());
+            let reply = status(db, from, &phone, received, language).await;
+            replies.push((from.to_string(), reply));
+            return Ok(Applied {
+                replies,
+                changed: false,
+            });
+        }
+        Shift(_) => {
+            let arrival = outgoing::arrival();
+            db.query(query, &[&from, &json, &received, &arrival])
+                .await?
+        }
+        _ => db.query(query, &[&from, &json]).await?,
+    };
+    let row = match rows.first() {
+        Some(row) => row,
+        None => {
+            return Ok(Applied {
+                replies,
+                changed: true,
+            })
+        }
+    };
+
+    match command {
+        Checkin(_) => {
+            let status = checkin_status(db, from).await;
+            let ack = outgoing::checkin_ack(status, received, language);
+            replies.push((from.to_string(), ack));
+        }
+        Reached(stop) | Skip(stop) => {
+            let reached = matches!(command, Reached(_));
+            let ack = outgoing::stop_marked(stop, reached, language);
+            replies.push((from.to_string(), ack));
+        }
+        Info(_) => {
+            let info: Value = row.get(4);
+            replies.push((from.to_string(), outgoing::info_reply(&info, language)));
+        }
+        Create(_) | Edit(_) | Add(_) | Insert(..) | Remove(_) | Shift(_) => {
+            let route: Option<Value> = row.get(2);
+            let summary = route.and_then(|route| outgoing::route_summary(from, &route, language));
+            if let Some(summary) = summary {
+                replies.push((from.to_string(), summary));
+            }
+        }
+        _ => (),
+    }
+    Ok(Applied {
+        replies,
+        changed: true,
+    })
+}
+
+// Records a distress call and alerts the contacts of the hiker and the rescue coordinator, even if
+// the position could not be resolved or recorded.
+async fn sos(
+    db: &Arc<tokio_postgres::Client>,
+    sms_out: &Arc<dyn SmsSender>,
+    db_tx: &mut mpsc::Sender<(String, String)>,
+    from: &str,
+    rescue: &Option<String>,
+    command: Command,
+) {
+    let json = serde_json::to_value(&command).unwrap_or(Value::Null);
+    let query = "SELECT * FROM public.sos($1, $2)";
+    let position = match db.query_opt(query, &[&from, &json]).await {
+        Ok(row) => row.map(|row| outgoing::Located {
+            latitude: row.get(0),
+            longitude: row.get(1),
+            zone: row.get::<_, i32>(2) as u32,
+            eastings: row.get(3),
+            northings: row.get(4),
+        }),
+        Err(e) => {
+            eprintln!("Could not record SOS: {}", e);
+            None
+        }
+    };
+    let given = json["point"]["position"]["srid"].is_number();
+    // Like other alerts about the hike, the call is relayed in the language of the hiker.
+    let language = stored_language(db, from).await;
+    let message = json["message"].as_str();
+    let alert = outgoing::sos_alert(from, message, position.as_ref(), given, language);
+
+    let mut recipients: Vec<String> = match db
+        .query("SELECT * FROM public.contacts($1)", &[&from])
+        .await
+    {
+        Ok(rows) => rows.iter().map(|row| row.get(0)).collect(),
+        Err(_) => vec![],
+    };
+    recipients.extend(rescue.clone());
+    let ack = outgoing::sos_ack(recipients.len(), language);
+    for recipient in recipients {
+        tokio::spawn(send_sms(
+            db.clone(),
+            sms_out.clone(),
+            recipient,
+            alert.clone(),
+        ));
+    }
+    tokio::spawn(send_sms(db.clone(), sms_out.clone(), from.to_string(), ack));
+
+    // Map viewers get a distinct event, so that the page can highlight the call.
+    if let Ok(rows) = db.query("SELECT * FROM public.hike($1)", &[&from]).await {
+        if !rows.is_empty() {
+            db_tx
+                .send((from.to_string(), convert_rows(rows, "sos")))
+                .await
+                .unwrap();
+        }
+    }
+}
+
+// Where the latest checkin is along the route, and the next stop after it.
+async fn checkin_status<C: GenericClient + Sync>(
+    db: &C,
+    phone: &str,
+) -> Option<(f64, Option<outgoing::Stop>)> {
+    let query = "SELECT * FROM public.checkin_status($1)";
+    let row = db.query_opt(query, &[&phone]).await.ok()??;
+    let date: Option<NaiveDate> = row.get(4);
+    let next = date.map(|date| outgoing::Stop {
+        label: row.get::<_, Option<String>>(2).or_else(|| row.get(3)),
+        date,
+        time: row.get(5),
+        remaining: row.get(1),
+    });
+    Some((row.get(0), next))
+}
+
+// The first stop still planned on the route, for hikes not checked in along it.
+async fn next_stop<C: GenericClient + Sync>(db: &C, phone: &str) -> Option<outgoing::Stop> {
+    let query = "SELECT * FROM public.next_stop($1)";
+    let row = db.query_opt(query, &[&phone]).await.ok()??;
+    let date: Option<NaiveDate> = row.get(3);
+    date.map(|date| outgoing::Stop {
+        label: row.get::<_, Option<String>>(1).or_else(|| row.get(2)),
+        date,
+        time: row.get(4),
+        remaining: row.get(0),
+    })
+}
+
+// Describes the hike of `phone` to `viewer`, if they are the hiker or one of their contacts.
+async fn status<C: GenericClient + Sync>(
+    db: &C,
+    viewer: &str,
+    phone: &str,
+    now: NaiveDateTime,
+    language: Language,
+) -> String {
+    match db
+        .query_one("SELECT public.may_view($1, $2)", &[&viewer, &phone])
+        .await
+    {
+        Ok(row) if row.get(0) => (),
+        _ => return outgoing::no_hike(phone, language),
+    }
+    let row = match db
+        .query_opt("SELECT * FROM public.last_checkin($1)", &[&phone])
+        .await
+    {
+        Ok(Some(row)) => row,
+        _ => return outgoing::no_hike(phone, language),
+    };
+
+    let logged: Option<DateTime<Local>> = row.get(0);
+    let checkin = logged.map(|logged| outgoing::Checkin {
+        label: row.get::<_, Option<String>>(1).or_else(|| row.get(2)),
+        logged: logged.naive_local(),
+    });
+    // Without a checkin along the route, the next stop is the first one still planned.
+    let next = match checkin_status(db, phone).await {
+        Some((_, next)) => next,
+        None => next_stop(db, phone).await,
+    };
+    let arrival = outgoing::arrival();
+    let query = "SELECT planned, level FROM public.overdue_hikes($1, $2) WHERE phone = $3";
+    let overdue = match db.query_opt(query, &[&now, &arrival, &phone]).await {
+        Ok(Some(row)) => Some((row.get(0), row.get(1))),
+        _ => None,
+    };
+    outgoing::status_reply(phone, checkin, next, overdue, language)
+}
+
+async fn connect(conn_string: &str) -> tokio_postgres::Client {
+    match tokio_postgres::connect(conn_string, NoTls).await {
+        Ok((db, connection)) => {
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("Database connection error: {}", e);
+                }
+            });
+            db
+        }
+        Err(_) => panic!("Could not connect to database."),
+    }
+}
+
 async fn ws_connect(
     ws: WebSocket,
     mut ws_tx: mpsc::Sender<(String, SplitSink<WebSocket, Message>)>,
@@ -176,14 +842,90 @@ async fn ws_connect(
     }
 }
 
-fn convert_rows(rows: Vec<Row>) -> String {
+// Sends a message, recording it in the database if it could not be delivered.
+async fn send_sms(
+    db: Arc<tokio_postgres::Client>,
+    sms_out: Arc<dyn SmsSender>,
+    to: String,
+    message: String,
+) {
+    if let Err(failure) = sms::send_with_retry(&*sms_out, &to, &message).await {
+        let error = failure.error.description();
+        eprintln!("Could not send SMS to {}: {}", to, error);
+        let attempts = failure.attempts as i32;
+        let _ = db
+            .query(
+                "SELECT public.record_failed_sms($1, $2, $3, $4)",
+                &[&to, &message, &error, &attempts],
+            )
+            .await;
+    }
+}
+
+// Messages to map viewers are tagged with an event, `hike` for updates and `sos` for
+// distress calls.
+fn convert_rows(rows: Vec<Row>, event: &str) -> String {
     let _id: Uuid = rows[0].get(0);
     let routes: Option<Value> = rows[0].get(2);
     let traces: Option<Value> = rows[0].get(3);
+    let info: Value = rows[0].get(4);
     json!({
+        "event": event,
         "_id": _id,
         "route": routes,
         "trace": traces,
+        "info": info,
     })
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffered(message: &str) -> Option<(String, bool)> {
+        Some((message.to_string(), false))
+    }
+
+    #[test]
+    fn joins_parts_of_an_itinerary() {
+        let joined = join(
+            "33W XR 2345 6789 +2d end",
+            buffered("edit 33W XR 1234 5678 +1d"),
+        );
+        assert_eq!(
+            joined.message,
+            "edit 33W XR 1234 5678 +1d 33W XR 2345 6789 +2d"
+        );
+        assert!(joined.marker == Some(command::Marker::End));
+        assert!(!joined.standalone);
+    }
+
+    #[test]
+    fn handles_sos_while_an_itinerary_is_pending() {
+        let joined = join("sos broken leg", buffered("edit 33W XR 1234 5678 +1d"));
+        assert_eq!(joined.message, "sos broken leg");
+        assert!(joined.marker.is_none());
+        assert!(joined.standalone);
+
+        let gazetteer = Gazetteer::default();
+        let context = Context {
+            gazetteer: &gazetteer,
+            received: Local::now().naive_local(),
+        };
+        match Command::parse(&joined.message, &context) {
+            Ok((commands, _)) => assert!(matches!(commands[..], [Command::Sos(_)])),
+            Err(err) => panic!("{}", err.description()),
+        }
+    }
+
+    #[test]
+    fn handles_other_commands_while_an_itinerary_is_pending() {
+        for message in &["status", "checkin 67.85 18.55", "undo", "sos more"] {
+            let joined = join(message, buffered("create 33W XR 1234 5678 +1d"));
+            assert_eq!(joined.message, *message);
+            assert!(joined.marker.is_none());
+            assert!(joined.standalone);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use super::outgoing;
+use super::stored_language;
+use crate::command::Language;
+use crate::error::Error;
+use crate::sms::{self, SmsSender};
+use async_trait::async_trait;
+use chrono::prelude::{Local, NaiveDateTime, NaiveTime};
+use chrono::Duration;
+use std::sync::Arc;
+use tokio_postgres::Client;
+
+// How often hikes are checked for missed stops.
+const INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+// Escalation levels stored in `hike.alert`; the hiker is alerted after one grace period, and the
+// emergency contacts after two.
+const ALERT_HIKER: i32 = 1;
+const ALERT_CONTACTS: i32 = 2;
+
+// The monitor reads the time through a clock, so that it can be driven by a fake one.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+// A missed stop, and the level the hike has been alerted at for it.
+pub struct Overdue {
+    phone: String,
+    planned: NaiveDateTime,
+    place: Option<String>,
+    alerted: i32,
+}
+
+// What the monitor reads and records about hikes, so that it can be run without a database.
+#[async_trait]
+pub trait Hikes: Send + Sync {
+    async fn overdue(&self, now: NaiveDateTime, arrival: NaiveTime)
+        -> Result<Vec<Overdue>, String>;
+    async fn contacts(&self, phone: &str) -> Result<Vec<String>, String>;
+    async fn language(&self, phone: &str) -> Language;
+    async fn record_alert(
+        &self,
+        phone: &str,
+        planned: NaiveDateTime,
+        level: i32,
+    ) -> Result<(), String>;
+    async fn record_failed_sms(&self, to: &str, message: &str, error: &str, attempts: u32);
+}
+
+#[async_trait]
+impl Hikes for Client {
+    async fn overdue(
+        &self,
+        now: NaiveDateTime,
+        arrival: NaiveTime,
+    ) -> Result<Vec<Overdue>, String> {
+        let query = "SELECT * FROM public.overdue_hikes($1, $2)";
+        let rows = self
+            .query(query, &[&now, &arrival])
+            .await
+            .map_err(|e| e.to_string())?;
+        let overdue = rows.iter().map(|row| Overdue {
+            phone: row.get(0),
+            planned: row.get(1),
+            place: row.get(2),
+            alerted: row.get(3),
+        });
+        Ok(overdue.collect())
+    }
+
+    async fn contacts(&self, phone: &str) -> Result<Vec<String>, String> {
+        let query = "SELECT * FROM public.contacts($1)";
+        let rows = self
+            .query(query, &[&phone])
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn language(&self, phone: &str) -> Language {
+        stored_language(self, phone).await
+    }
+
+    async fn record_alert(
+        &self,
+        phone: &str,
+        planned: NaiveDateTime,
+        level: i32,
+    ) -> Result<(), String> {
+        let query = "SELECT public.record_alert($1, $2, $3)";
+        self.query(query, &[&phone, &planned, &level])
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn record_failed_sms(&self, to: &str, message: &str, error: &str, attempts: u32) {
+        let attempts = attempts as i32;
+        let _ = self
+            .query(
+                "SELECT public.record_failed_sms($1, $2, $3, $4)",
+                &[&to, &message, &error, &attempts],
+            )
+            .await;
+    }
+}
+
+pub async fn monitor(
+    db: Arc<Client>,
+    sms_out: Arc<dyn SmsSender>,
+    clock: Arc<dyn Clock>,
+    grace: Duration,
+) {
+    let mut interval = tokio::time::interval(INTERVAL);
+    loop {
+        interval.tick().await;
+        check(&*db, &*sms_out, &*clock, grace).await;
+    }
+}
+
+// Sends the alerts that are due at the time given by the clock. A hike first found long overdue
+// is escalated one level at a time, so that the hiker is always alerted before the contacts.
+pub async fn check(hikes: &dyn Hikes, sms_out: &dyn SmsSender, clock: &dyn Clock, grace: Duration) {
+    let now = clock.now();
+    let overdue = match hikes.overdue(now, outgoing::arrival()).await {
+        Ok(overdue) => overdue,
+        Err(e) => {
+            eprintln!("Could not check for overdue hikes: {}", e);
+            return;
+        }
+    };
+
+    for hike in overdue {
+        let level = match escalation(hike.planned, hike.alerted, now, grace) {
+            Some(level) => level,
+            None => continue,
+        };
+        // Contacts are alerted in the language of the hiker.
+        let language = hikes.language(&hike.phone).await;
+
+        for level in hike.alerted + 1..=level {
+            let recipients = if level == ALERT_HIKER {
+                vec![hike.phone.clone()]
+            } else {
+                match hikes.contacts(&hike.phone).await {
+                    Ok(contacts) => contacts,
+                    Err(_) => break,
+                }
+            };
+            let to_hiker = level == ALERT_HIKER;
+            let message = outgoing::overdue_alert(
+                &hike.phone,
+                hike.place.as_deref(),
+                hike.planned,
+                to_hiker,
+                language,
+            );
+            for recipient in recipients {
+                alert(hikes, sms_out, &recipient, &message).await;
+            }
+
+            if let Err(e) = hikes.record_alert(&hike.phone, hike.planned, level).await {
+                eprintln!("Could not record alert: {}", e);
+            }
+        }
+    }
+}
+
+// Alerts are sent one after the other, in the order they escalate.
+async fn alert(hikes: &dyn Hikes, sms_out: &dyn SmsSender, to: &str, message: &str) {
+    if let Err(failure) = sms::send_with_retry(sms_out, to, message).await {
+        let error = failure.error.description();
+        eprintln!("Could not send SMS to {}: {}", to, error);
+        hikes
+            .record_failed_sms(to, message, &error, failure.attempts)
+            .await;
+    }
+}
+
+// The level to escalate to for a stop planned at `planned`, if it is higher than `alerted`.
+fn escalation(
+    planned: NaiveDateTime,
+    alerted: i32,
+    now: NaiveDateTime,
+    grace: Duration,
+) -> Option<i32> {
+    let overdue = now - planned;
+    let level = if overdue >= grace * 2 {
+        ALERT_CONTACTS
+    } else if overdue >= grace {
+        ALERT_HIKER
+    } else {
+        return None;
+    };
+    if level > alerted {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sms::Recorder;
+    use chrono::NaiveDate;
+    use std::sync::Mutex;
+
+    // A clock that stands still at the given time.
+    struct FakeClock(NaiveDateTime);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> NaiveDateTime {
+            self.0
+        }
+    }
+
+    fn planned() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 7, 1)
+            .and_then(|date| date.and_hms_opt(18, 0, 0))
+            .unwrap()
+    }
+
+    fn escalate_after(hours: i64, alerted: i32) -> Option<i32> {
+        let clock = FakeClock(planned() + Duration::hours(hours));
+        escalation(planned(), alerted, clock.now(), Duration::hours(6))
+    }
+
+    #[test]
+    fn waits_out_grace() {
+        assert_eq!(escalate_after(-1, 0), None);
+        assert_eq!(escalate_after(5, 0), None);
+    }
+
+    #[test]
+    fn alerts_hiker_after_grace() {
+        assert_eq!(escalate_after(6, 0), Some(ALERT_HIKER));
+        assert_eq!(escalate_after(11, 0), Some(ALERT_HIKER));
+    }
+
+    #[test]
+    fn alerts_contacts_after_twice_grace() {
+        assert_eq!(escalate_after(12, 0), Some(ALERT_CONTACTS));
+        assert_eq!(escalate_after(12, ALERT_HIKER), Some(ALERT_CONTACTS));
+    }
+
+    const HIKER: &str = "+46701234567";
+    const CONTACT: &str = "+46707654321";
+
+    // A hike that missed a stop, alerted at the levels recorded so far, with one emergency contact.
+    #[derive(Default)]
+    struct Stub {
+        recorded: Mutex<Vec<i32>>,
+    }
+
+    #[async_trait]
+    impl Hikes for Stub {
+        async fn overdue(&self, _: NaiveDateTime, _: NaiveTime) -> Result<Vec<Overdue>, String> {
+            let alerted = self.recorded.lock().unwrap().last().copied().unwrap_or(0);
+            Ok(vec![Overdue {
+                phone: HIKER.to_string(),
+                planned: planned(),
+                place: Some("Abisko".to_string()),
+                alerted,
+            }])
+        }
+
+        async fn contacts(&self, _: &str) -> Result<Vec<String>, String> {
+            Ok(vec![CONTACT.to_string()])
+        }
+
+        async fn language(&self, _: &str) -> Language {
+            Language::English
+        }
+
+        async fn record_alert(&self, _: &str, _: NaiveDateTime, level: i32) -> Result<(), String> {
+            self.recorded.lock().unwrap().push(level);
+            Ok(())
+        }
+
+        async fn record_failed_sms(&self, _: &str, _: &str, _: &str, _: u32) {}
+    }
+
+    fn recipients(recorder: &Recorder) -> Vec<String> {
+        recorder.sent().into_iter().map(|(to, _)| to).collect()
+    }
+
+    #[tokio::test]
+    async fn alerts_hiker_before_contacts_when_first_checked_late() {
+        let (hikes, recorder) = (Stub::default(), Recorder::default());
+        let clock = FakeClock(planned() + Duration::hours(13));
+
+        check(&hikes, &recorder, &clock, Duration::hours(6)).await;
+
+        assert_eq!(recipients(&recorder), vec![HIKER, CONTACT]);
+        assert_eq!(
+            *hikes.recorded.lock().unwrap(),
+            vec![ALERT_HIKER, ALERT_CONTACTS]
+        );
+    }
+
+    #[tokio::test]
+    async fn escalates_once_per_level() {
+        let (hikes, recorder) = (Stub::default(), Recorder::default());
+        let grace = Duration::hours(6);
+
+        check(
+            &hikes,
+            &recorder,
+            &FakeClock(planned() + Duration::hours(7)),
+            grace,
+        )
+        .await;
+        check(
+            &hikes,
+            &recorder,
+            &FakeClock(planned() + Duration::hours(8)),
+            grace,
+        )
+        .await;
+        assert_eq!(recipients(&recorder), vec![HIKER]);
+
+        check(
+            &hikes,
+            &recorder,
+            &FakeClock(planned() + Duration::hours(12)),
+            grace,
+        )
+        .await;
+        assert_eq!(recipients(&recorder), vec![HIKER, CONTACT]);
+    }
+
+    #[test]
+    fn does_not_repeat_alerts() {
+        assert_eq!(escalate_after(7, ALERT_HIKER), None);
+        assert_eq!(escalate_after(13, ALERT_CONTACTS), None);
+        assert_eq!(escalate_after(48, ALERT_CONTACTS), None);
+    }
+}
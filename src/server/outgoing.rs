@@ -0,0 +1,734 @@
+use crate::command::{mgrs, Contact, Language};
+use crate::MAP_URL;
+use chrono::prelude::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::Duration;
+use serde_json::Value;
+use Language::*;
+
+// Replies should fit in a single SMS: 160 characters of the GSM 03.38 alphabet, which covers the
+// Nordic letters, or else 70 UTF-16 code units.
+const GSM_LENGTH: usize = 160;
+const UCS2_LENGTH: usize = 70;
+const GSM_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ \
+                         !\"#¤%&'()*+,-./0123456789:;<=>?¡\
+                         ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿\
+                         abcdefghijklmnopqrstuvwxyzäöñüà";
+// Characters of the GSM 03.38 extension table, which take two septets each.
+const GSM_EXTENSION: &str = "^{}\\[~]|€\u{c}";
+// Used for estimating arrival, in km/h, including breaks.
+const WALKING_SPEED: f64 = 3.0;
+// Stops planned without a time are assumed to be reached in the evening.
+const ARRIVAL_HOUR: u32 = 18;
+
+// The time of day at which stops planned without a time are expected.
+pub fn arrival() -> NaiveTime {
+    NaiveTime::from_hms_opt(ARRIVAL_HOUR, 0, 0).expect("arrival hour is a valid time")
+}
+
+// The latest checkin, labelled by its place or message.
+pub struct Checkin {
+    pub label: Option<String>,
+    pub logged: NaiveDateTime,
+}
+
+// A position in WGS 84 and in the UTM zone of its longitude.
+pub struct Located {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub zone: u32,
+    pub eastings: f64,
+    pub northings: f64,
+}
+
+// The first planned stop after a checkin, `remaining` metres along the route.
+pub struct Stop {
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub remaining: f64,
+}
+
+// Replies are given in the language of the request, and alerts about a hike in the language of the
+// hiker.
+
+// Summarises a stored route with its number of stops and first and last dates, listing the stops
+// if they fit in one SMS. The route is GeoJSON in ETRS89 / UTM 33N, so distances are planar.
+pub fn route_summary(phone: &str, route: &Value, language: Language) -> Option<String> {
+    let features = route["features"].as_array()?;
+    let link = map_link(phone);
+
+    let mut stops = vec![];
+    let mut dates = vec![];
+    let mut distance = 0.0;
+    let mut previous: Option<(f64, f64)> = None;
+    for feature in features {
+        let coordinates = &feature["geometry"]["coordinates"];
+        let point = (coordinates[0].as_f64()?, coordinates[1].as_f64()?);
+        if let Some((x, y)) = previous {
+            distance += ((point.0 - x).powi(2) + (point.1 - y).powi(2)).sqrt();
+        }
+        previous = Some(point);
+
+        let properties = &feature["properties"];
+        let date = properties["date"].as_str().unwrap_or("");
+        let label = properties["place"]
+            .as_str()
+            .or_else(|| properties["message"].as_str());
+        // Dates are ISO 8601, so the month and day are the last five characters.
+        let short_date = &date[date.len().saturating_sub(5)..];
+        stops.push(match label {
+            Some(label) => format!("{} {}", label, short_date).trim_end().to_string(),
+            None => short_date.to_string(),
+        });
+        if !date.is_empty() {
+            dates.push(date);
+        }
+    }
+    let count = stops.len();
+    let km = distance / 1000.0;
+
+    let saved = match language {
+        English => format!("Route saved: {} stops", count),
+        Swedish => format!("Rutt sparad: {} stopp", count),
+        Norwegian => format!("Rute lagret: {} stopp", count),
+    };
+    let dated = match (language, dates.iter().min(), dates.iter().max()) {
+        (English, Some(first), Some(last)) => format!("{}, {} to {}", saved, first, last),
+        (Swedish, Some(first), Some(last)) => format!("{}, {} till {}", saved, first, last),
+        (Norwegian, Some(first), Some(last)) => format!("{}, {} til {}", saved, first, last),
+        _ => saved.clone(),
+    };
+    let map = match language {
+        English => format!("{:.1} km. Map: {}", km, link),
+        Swedish => format!("{:.1} km. Karta: {}", km, link),
+        Norwegian => format!("{:.1} km. Kart: {}", km, link),
+    };
+    // The stops are left out if they do not fit, and then the dates. A summary that does not fit
+    // even so, as when a stop name needs UCS-2, is sent in several parts.
+    let summaries = vec![
+        format!("{}: {}. {}", dated, stops.join(", "), map),
+        format!("{}, {}", dated, map),
+        format!("{}, {}", saved, map),
+    ];
+    let fallback = summaries.last().cloned();
+    summaries
+        .into_iter()
+        .find(|summary| fits_sms(summary))
+        .or(fallback)
+}
+
+// Whether the message fits in a single SMS, in the encoding it will be sent in.
+fn fits_sms(message: &str) -> bool {
+    let mut septets = 0;
+    for c in message.chars() {
+        if GSM_BASIC.contains(c) {
+            septets += 1;
+        } else if GSM_EXTENSION.contains(c) {
+            septets += 2;
+        } else {
+            return message.encode_utf16().count() <= UCS2_LENGTH;
+        }
+    }
+    septets <= GSM_LENGTH
+}
+
+// Acknowledges a checkin made `distance` metres from the route, estimating when the next stop is
+// reached and whether that is ahead of or behind the plan. Checkins without a route are only
+// acknowledged.
+pub fn checkin_ack(
+    status: Option<(f64, Option<Stop>)>,
+    received: NaiveDateTime,
+    language: Language,
+) -> String {
+    let (distance, next) = match status {
+        Some(status) => status,
+        None => {
+            return match language {
+                English => "Checked in.",
+                Swedish => "Incheckad.",
+                Norwegian => "Sjekket inn.",
+            }
+            .to_string()
+        }
+    };
+    let km = distance / 1000.0;
+    let checked_in = match language {
+        English => format!("Checked in {:.1} km from route.", km),
+        Swedish => format!("Incheckad {:.1} km från rutten.", km),
+        Norwegian => format!("Sjekket inn {:.1} km fra ruten.", km),
+    };
+    let next = match next {
+        Some(next) => next,
+        None => {
+            return match language {
+                English => format!("{} No planned stops ahead.", checked_in),
+                Swedish => format!("{} Inga planerade stopp kvar.", checked_in),
+                Norwegian => format!("{} Ingen planlagte stopp igjen.", checked_in),
+            }
+        }
+    };
+
+    let hours = next.remaining / 1000.0 / WALKING_SPEED;
+    let eta = received + Duration::minutes((hours * 60.0).round() as i64);
+    let planned = next.date.and_time(next.time.unwrap_or_else(arrival));
+    let ahead = (planned - eta).num_hours();
+    let schedule = match (language, ahead) {
+        (English, 0) => "on schedule".to_string(),
+        (Swedish, 0) => "enligt plan".to_string(),
+        (Norwegian, 0) => "i rute".to_string(),
+        (English, ahead) if ahead > 0 => format!("{} h ahead", ahead),
+        (Swedish, ahead) if ahead > 0 => format!("{} h före", ahead),
+        (Norwegian, ahead) if ahead > 0 => format!("{} t foran", ahead),
+        (English, behind) => format!("{} h behind", -behind),
+        (Swedish, behind) => format!("{} h efter", -behind),
+        (Norwegian, behind) => format!("{} t bak", -behind),
+    };
+
+    let planned = match next.time {
+        Some(time) => format!("{} {}", next.date.format("%m-%d"), time.format("%H:%M")),
+        None => next.date.format("%m-%d").to_string(),
+    };
+    let planned = match language {
+        English => format!("planned {}", planned),
+        Swedish => format!("planerat {}", planned),
+        Norwegian => format!("planlagt {}", planned),
+    };
+    let stop = match next.label {
+        Some(label) => format!("{}, {}", label, planned),
+        None => planned,
+    };
+    let remaining = next.remaining / 1000.0;
+    let eta = eta.format("%m-%d %H:%M");
+    match language {
+        English => format!(
+            "{} Next: {}, {:.1} km, ETA {}, {}.",
+            checked_in, stop, remaining, eta, schedule
+        ),
+        Swedish => format!(
+            "{} Nästa: {}, {:.1} km, beräknad {}, {}.",
+            checked_in, stop, remaining, eta, schedule
+        ),
+        Norwegian => format!(
+            "{} Neste: {}, {:.1} km, beregnet {}, {}.",
+            checked_in, stop, remaining, eta, schedule
+        ),
+    }
+}
+
+// Asks an overdue hiker to check in, or tells their contacts that they have not.
+pub fn overdue_alert(
+    phone: &str,
+    place: Option<&str>,
+    planned: NaiveDateTime,
+    to_hiker: bool,
+    language: Language,
+) -> String {
+    let planned = planned.format("%m-%d %H:%M");
+    let stop = match (language, place) {
+        (English, Some(place)) => format!("at {} by {}", place, planned),
+        (Swedish, Some(place)) => format!("vid {} senast {}", place, planned),
+        (Norwegian, Some(place)) => format!("ved {} innen {}", place, planned),
+        (English, None) => format!("by {}", planned),
+        (Swedish, None) => format!("senast {}", planned),
+        (Norwegian, None) => format!("innen {}", planned),
+    };
+    let link = map_link(phone);
+    match (language, to_hiker) {
+        (English, true) => format!(
+            "You were expected {} but have not checked in. Check in now, or your emergency \
+             contacts will be alerted.",
+            stop
+        ),
+        (Swedish, true) => format!(
+            "Du väntades {} men har inte checkat in. Checka in nu, annars larmas dina \
+             nödkontakter.",
+            stop
+        ),
+        (Norwegian, true) => format!(
+            "Du var ventet {} men har ikke sjekket inn. Sjekk inn nå, ellers varsles \
+             nødkontaktene dine.",
+            stop
+        ),
+        (English, false) => format!(
+            "{} was expected {} and has not checked in. Last known route: {}",
+            phone, stop, link
+        ),
+        (Swedish, false) => format!(
+            "{} väntades {} och har inte checkat in. Senast kända rutt: {}",
+            phone, stop, link
+        ),
+        (Norwegian, false) => format!(
+            "{} var ventet {} og har ikke sjekket inn. Sist kjente rute: {}",
+            phone, stop, link
+        ),
+    }
+}
+
+// Where and what to reply when a contact was changed: new contacts are asked to opt in, and
+// contacts that did are thanked. The hiker is told what became of the contact, except when it
+// was already active. Removed contacts are not told.
+pub fn contact_replies(
+    from: &str,
+    contact: &Contact,
+    pending: bool,
+    language: Language,
+) -> Vec<(String, String)> {
+    match contact {
+        Contact::Add { phone, .. } if pending => {
+            let request = match language {
+                English => format!(
+                    "{} wants you as emergency contact during their hike, to be alerted if they \
+                     are overdue. Reply \"contact accept\" to agree.",
+                    from
+                ),
+                Swedish => format!(
+                    "{} vill ha dig som nödkontakt under sin vandring, och larma dig vid \
+                     försening. Svara \"kontakt godkänn\" för att godkänna.",
+                    from
+                ),
+                Norwegian => format!(
+                    "{} vil ha deg som nødkontakt på turen, og varsle deg ved forsinkelse. Svar \
+                     \"kontakt godta\" for å godta.",
+                    from
+                ),
+            };
+            let added = match language {
+                English => format!("Added contact {}, who is asked to accept.", phone),
+                Swedish => format!("Kontakten {} är tillagd och ombedd att godkänna.", phone),
+                Norwegian => format!("Kontakten {} er lagt til og bedt om å godta.", phone),
+            };
+            vec![(phone.clone(), request), (from.to_string(), added)]
+        }
+        Contact::Add { phone, .. } => {
+            let active = match language {
+                English => format!("{} is already your emergency contact.", phone),
+                Swedish => format!("{} är redan din nödkontakt.", phone),
+                Norwegian => format!("{} er allerede din nødkontakt.", phone),
+            };
+            vec![(from.to_string(), active)]
+        }
+        Contact::Remove { phone } => {
+            let removed = match language {
+                English => format!("Removed contact {}.", phone),
+                Swedish => format!("Kontakten {} är borttagen.", phone),
+                Norwegian => format!("Kontakten {} er fjernet.", phone),
+            };
+            vec![(from.to_string(), removed)]
+        }
+        Contact::Accept => {
+            let thanks = match language {
+                English => "Thank you. You will be alerted if the hiker is overdue.",
+                Swedish => "Tack. Du larmas om vandraren är försenad.",
+                Norwegian => "Takk. Du blir varslet om turgåeren er forsinket.",
+            };
+            vec![(from.to_string(), thanks.to_string())]
+        }
+    }
+}
+
+pub fn contact_accepted(contact: &str, language: Language) -> String {
+    match language {
+        English => format!("{} accepted to be your emergency contact.", contact),
+        Swedish => format!("{} har godkänt att vara din nödkontakt.", contact),
+        Norwegian => format!("{} har godtatt å være din nødkontakt.", contact),
+    }
+}
+
+// Relays the message of a distress call, with its position in several formats, as rescuers may
+// use any of them. Without a position given in the call, the last known one is used.
+pub fn sos_alert(
+    phone: &str,
+    text: Option<&str>,
+    position: Option<&Located>,
+    given: bool,
+    language: Language,
+) -> String {
+    let position = match position {
+        Some(position) => {
+            let latitude = hemisphere(position.latitude, 'N', 'S');
+            let longitude = hemisphere(position.longitude, 'E', 'W');
+            let mut formats = vec![
+                format!(
+                    "UTM {}N {:.0} {:.0}",
+                    position.zone, position.eastings, position.northings
+                ),
+                format!("{} {}", latitude, longitude),
+            ];
+            if let Some(reference) = mgrs::format(
+                position.zone,
+                position.latitude,
+                position.eastings,
+                position.northings,
+            ) {
+                formats.push(format!("MGRS {}", reference));
+            }
+            let known = match (language, given) {
+                (English, true) => "Position",
+                (Swedish, true) => "Position",
+                (Norwegian, true) => "Posisjon",
+                (English, false) => "Last known position",
+                (Swedish, false) => "Senast kända position",
+                (Norwegian, false) => "Sist kjente posisjon",
+            };
+            format!("{}: {}.", known, formats.join(", "))
+        }
+        None => match language {
+            English => "Position unknown.",
+            Swedish => "Position okänd.",
+            Norwegian => "Posisjon ukjent.",
+        }
+        .to_string(),
+    };
+    let text = match text.map(str::trim).filter(|text| !text.is_empty()) {
+        Some(text) => format!(" \"{}\"", text),
+        None => String::new(),
+    };
+    let link = map_link(phone);
+    match language {
+        English => format!("SOS from {}:{} {} Map: {}", phone, text, position, link),
+        Swedish => format!("SOS från {}:{} {} Karta: {}", phone, text, position, link),
+        Norwegian => format!("SOS fra {}:{} {} Kart: {}", phone, text, position, link),
+    }
+}
+
+pub fn sos_ack(recipients: usize, language: Language) -> String {
+    match (language, recipients) {
+        (English, 0) => {
+            "SOS received, but you have no emergency contacts to alert. Call 112.".to_string()
+        }
+        (Swedish, 0) => {
+            "SOS mottaget, men du har inga nödkontakter att larma. Ring 112.".to_string()
+        }
+        (Norwegian, 0) => {
+            "SOS mottatt, men du har ingen nødkontakter å varsle. Ring 112.".to_string()
+        }
+        (English, 1) => "SOS sent to 1 recipient.".to_string(),
+        (English, n) => format!("SOS sent to {} recipients.", n),
+        (Swedish, n) => format!("SOS skickat till {} mottagare.", n),
+        (Norwegian, 1) => "SOS sendt til 1 mottaker.".to_string(),
+        (Norwegian, n) => format!("SOS sendt til {} mottakere.", n),
+    }
+}
+
+fn hemisphere(degrees: f64, positive: char, negative: char) -> String {
+    if degrees < 0.0 {
+        format!("{:.5}{}", -degrees, negative)
+    } else {
+        format!("{:.5}{}", degrees, positive)
+    }
+}
+
+// Describes a hike: its latest checkin, the next stop, and whether it is overdue at a stop
+// planned at the given time, with the alert level reached.
+pub fn status_reply(
+    phone: &str,
+    checkin: Option<Checkin>,
+    next: Option<Stop>,
+    overdue: Option<(NaiveDateTime, i32)>,
+    language: Language,
+) -> String {
+    let mut parts = vec![];
+    parts.push(match checkin {
+        Some(Checkin { label, logged }) => {
+            let logged = logged.format("%m-%d %H:%M");
+            match (language, label) {
+                (English, Some(label)) => format!("Last checkin {} at {}.", logged, label),
+                (Swedish, Some(label)) => format!("Senaste incheckning {} vid {}.", logged, label),
+                (Norwegian, Some(label)) => format!("Siste innsjekk {} ved {}.", logged, label),
+                (English, None) => format!("Last checkin {}.", logged),
+                (Swedish, None) => format!("Senaste incheckning {}.", logged),
+                (Norwegian, None) => format!("Siste innsjekk {}.", logged),
+            }
+        }
+        None => match language {
+            English => "No checkins yet.",
+            Swedish => "Inga incheckningar än.",
+            Norwegian => "Ingen innsjekker ennå.",
+        }
+        .to_string(),
+    });
+    if let Some(next) = next {
+        let planned = next.date.format("%m-%d");
+        parts.push(match (language, next.label) {
+            (English, Some(label)) => format!("Next: {}, planned {}.", label, planned),
+            (Swedish, Some(label)) => format!("Nästa: {}, planerat {}.", label, planned),
+            (Norwegian, Some(label)) => format!("Neste: {}, planlagt {}.", label, planned),
+            (English, None) => format!("Next stop planned {}.", planned),
+            (Swedish, None) => format!("Nästa stopp planerat {}.", planned),
+            (Norwegian, None) => format!("Neste stopp planlagt {}.", planned),
+        });
+    }
+    parts.push(match overdue {
+        Some((planned, level)) => {
+            let alerted = match (language, level) {
+                (English, 0) => "no one alerted yet",
+                (Swedish, 0) => "ingen larmad än",
+                (Norwegian, 0) => "ingen varslet ennå",
+                (English, 1) => "hiker alerted",
+                (Swedish, 1) => "vandraren larmad",
+                (Norwegian, 1) => "turgåeren varslet",
+                (English, _) => "contacts alerted",
+                (Swedish, _) => "kontakterna larmade",
+                (Norwegian, _) => "kontaktene varslet",
+            };
+            let planned = planned.format("%m-%d %H:%M");
+            match language {
+                English => format!("Overdue since {}, {}.", planned, alerted),
+                Swedish => format!("Försenad sedan {}, {}.", planned, alerted),
+                Norwegian => format!("Forsinket siden {}, {}.", planned, alerted),
+            }
+        }
+        None => match language {
+            English => "Not overdue.",
+            Swedish => "Inte försenad.",
+            Norwegian => "Ikke forsinket.",
+        }
+        .to_string(),
+    });
+    format!("{}: {}", phone, parts.join(" "))
+}
+
+// The stored details of the hike, for the hiker to check.
+pub fn info_reply(info: &Value, language: Language) -> String {
+    let (party, tent, experience) = match language {
+        English => ("party", "tent", "experience"),
+        Swedish => ("antal", "tält", "erfarenhet"),
+        Norwegian => ("antall", "telt", "erfaring"),
+    };
+    let mut details = vec![];
+    if let Some(name) = info["name"].as_str() {
+        details.push(name.to_string());
+    }
+    if let Some(value) = info["party"].as_i64() {
+        details.push(format!("{} {}", party, value));
+    }
+    if let Some(value) = info["tent"].as_str() {
+        details.push(format!("{} {}", tent, value));
+    }
+    if let Some(value) = info["experience"].as_str() {
+        details.push(format!("{} {}", experience, value));
+    }
+    format!("Info: {}.", details.join(", "))
+}
+
+pub fn awaiting_more(language: Language) -> String {
+    match language {
+        English => "Part received. Send the rest, ending the last part with \"end\".",
+        Swedish => "Del mottagen. Skicka resten, och avsluta sista delen med \"slut\".",
+        Norwegian => "Del mottatt. Send resten, og avslutt siste del med \"slutt\".",
+    }
+    .to_string()
+}
+
+// Points the sender at the word where their message could not be read.
+pub fn not_parsed(description: &str, fragment: &str, language: Language) -> String {
+    match (language, fragment) {
+        (English, "") => format!("{} at end of message.", description),
+        (Swedish, "") => format!("{} i slutet av meddelandet.", description),
+        (Norwegian, "") => format!("{} på slutten av meldingen.", description),
+        (English, _) => format!("{} near \"{}\".", description, fragment),
+        (Swedish, _) => format!("{} vid \"{}\".", description, fragment),
+        (Norwegian, _) => format!("{} ved \"{}\".", description, fragment),
+    }
+}
+
+// A message with several commands is applied as a whole, failing with its first failing command.
+pub fn not_applied(failed: Option<usize>, count: usize, language: Language) -> String {
+    if count == 1 {
+        return match language {
+            English => "The command could not be applied.",
+            Swedish => "Kommandot kunde inte utföras.",
+            Norwegian => "Kommandoen kunne ikke utføres.",
+        }
+        .to_string();
+    }
+    match (language, failed) {
+        (English, Some(failed)) => format!(
+            "Command {} of {} failed, so none of them were applied.",
+            failed, count
+        ),
+        (Swedish, Some(failed)) => format!(
+            "Kommando {} av {} misslyckades, så inget av dem utfördes.",
+            failed, count
+        ),
+        (Norwegian, Some(failed)) => format!(
+            "Kommando {} av {} feilet, så ingen av dem ble utført.",
+            failed, count
+        ),
+        (English, None) => format!("Could not apply the {} commands, none were applied.", count),
+        (Swedish, None) => format!("Kunde inte utföra de {} kommandona, inget utfördes.", count),
+        (Norwegian, None) => format!(
+            "Kunne ikke utføre de {} kommandoene, ingen ble utført.",
+            count
+        ),
+    }
+}
+
+// Why the database rejected a command, for the reasons that are the hiker's to fix.
+pub fn rejected(message: &str, language: Language) -> Option<String> {
+    let reason = match (message, language) {
+        ("Stop does not exist in route!", English) => "There is no such stop in the route",
+        ("Stop does not exist in route!", Swedish) => "Det finns inget sådant stopp i rutten",
+        ("Stop does not exist in route!", Norwegian) => "Det finnes ikke noe slikt stopp i ruten",
+        ("Route does not exist for given hike!", English) => "Your hike has no route",
+        ("Route does not exist for given hike!", Swedish) => "Din vandring har ingen rutt",
+        ("Route does not exist for given hike!", Norwegian) => "Turen din har ingen rute",
+        ("Hike does not exist for given phone number!", English) => "You have no hike",
+        ("Hike does not exist for given phone number!", Swedish) => "Du har ingen vandring",
+        ("Hike does not exist for given phone number!", Norwegian) => "Du har ingen tur",
+        _ => return None,
+    };
+    Some(reason.to_string())
+}
+
+pub fn stop_marked(stop: u32, reached: bool, language: Language) -> String {
+    match (language, reached) {
+        (English, true) => format!("Stop {} marked as reached.", stop),
+        (Swedish, true) => format!("Stopp {} markerat som nått.", stop),
+        (Norwegian, true) => format!("Stopp {} markert som nådd.", stop),
+        (English, false) => format!("Stop {} marked as skipped.", stop),
+        (Swedish, false) => format!("Stopp {} markerat som överhoppat.", stop),
+        (Norwegian, false) => format!("Stopp {} markert som hoppet over.", stop),
+    }
+}
+
+// The route revision or checkin reverted by an undo.
+pub struct Undone {
+    pub undone: String,
+    pub label: Option<String>,
+    pub logged: NaiveDateTime,
+    pub stops: Option<i32>,
+}
+
+pub fn undo_reply(undone: Option<Undone>, language: Language) -> String {
+    let undone = match undone {
+        Some(undone) => undone,
+        None => {
+            return match language {
+                English => "Nothing to undo.",
+                Swedish => "Inget att ångra.",
+                Norwegian => "Ingenting å angre.",
+            }
+            .to_string()
+        }
+    };
+    let logged = undone.logged.format("%m-%d %H:%M");
+    if undone.undone == "route" {
+        let stops = undone.stops.unwrap_or(0);
+        return match language {
+            English => format!(
+                "Undid route change from {}. The route has {} stops again.",
+                logged, stops
+            ),
+            Swedish => format!(
+                "Ångrade ruttändringen från {}. Rutten har {} stopp igen.",
+                logged, stops
+            ),
+            Norwegian => format!(
+                "Angret ruteendringen fra {}. Ruten har {} stopp igjen.",
+                logged, stops
+            ),
+        };
+    }
+    match (language, undone.label) {
+        (English, Some(label)) => format!("Undid checkin at {} from {}.", label, logged),
+        (Swedish, Some(label)) => format!("Ångrade incheckningen vid {} från {}.", label, logged),
+        (Norwegian, Some(label)) => format!("Angret innsjekken ved {} fra {}.", label, logged),
+        (English, None) => format!("Undid checkin from {}.", logged),
+        (Swedish, None) => format!("Ångrade incheckningen från {}.", logged),
+        (Norwegian, None) => format!("Angret innsjekken fra {}.", logged),
+    }
+}
+
+pub fn no_hike(phone: &str, language: Language) -> String {
+    match language {
+        English => format!("No hike you may view for {}.", phone),
+        Swedish => format!("Ingen vandring du får se för {}.", phone),
+        Norwegian => format!("Ingen tur du kan se for {}.", phone),
+    }
+}
+
+pub fn map_link(phone: &str) -> String {
+    format!("{}?map={}", MAP_URL, phone.trim_start_matches('+'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PHONE: &str = "+46701234567";
+
+    // A route with a stop named after each place, one day and one kilometre apart.
+    fn route(places: &[&str]) -> Value {
+        let features: Vec<Value> = places
+            .iter()
+            .enumerate()
+            .map(|(i, place)| {
+                let northings = 7_500_000.0 + i as f64 * 1000.0;
+                json!({
+                    "type": "Feature",
+                    "geometry": {"type": "Point", "coordinates": [650_000.0, northings]},
+                    "properties": {"place": place, "date": format!("2020-07-{:02}", i + 1)},
+                })
+            })
+            .collect();
+        json!({"type": "FeatureCollection", "features": features})
+    }
+
+    #[test]
+    fn lists_the_stops_of_short_routes() {
+        let summary = route_summary(PHONE, &route(&["Abisko", "Alesjaure"]), English);
+        let expected = "Route saved: 2 stops, 2020-07-01 to 2020-07-02: Abisko 07-01, \
+                        Alesjaure 07-02. 1.0 km. \
+                        Map: https://fiordland.antarkt.is/map?map=46701234567";
+        assert_eq!(summary.as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn counts_the_stops_of_long_routes() {
+        let places = [
+            "Abisko",
+            "Abiskojaure",
+            "Alesjaure",
+            "Tjäktja",
+            "Sälka",
+            "Singi",
+            "Kebnekaise fjällstation",
+            "Nikkaluokta",
+        ];
+        let summary = route_summary(PHONE, &route(&places), Swedish).unwrap();
+        let expected = "Rutt sparad: 8 stopp, 2020-07-01 till 2020-07-08, 7.0 km. \
+                        Karta: https://fiordland.antarkt.is/map?map=46701234567";
+        assert_eq!(summary, expected);
+        assert!(fits_sms(&summary));
+    }
+
+    #[test]
+    fn tells_the_hiker_about_contacts() {
+        let contact = "+4791234567".to_string();
+        let added = Contact::Add {
+            phone: contact.clone(),
+            name: None,
+        };
+        let recipients = |pending| -> Vec<String> {
+            let replies = contact_replies(PHONE, &added, pending, English);
+            replies.into_iter().map(|(to, _)| to).collect()
+        };
+        assert_eq!(recipients(true), vec![contact.as_str(), PHONE]);
+        assert_eq!(recipients(false), vec![PHONE]);
+        assert_eq!(
+            contact_replies(PHONE, &added, false, English)[0].1,
+            "+4791234567 is already your emergency contact."
+        );
+    }
+
+    #[test]
+    fn measures_messages_in_their_encoding() {
+        assert!(fits_sms(&"å".repeat(160)));
+        assert!(!fits_sms(&"å".repeat(161)));
+        assert!(!fits_sms(&"€".repeat(81)));
+        assert!(fits_sms(&"ŋ".repeat(70)));
+        assert!(!fits_sms(&format!("ŋ{}", "a".repeat(70))));
+    }
+}
@@ -0,0 +1,98 @@
+use super::{SendError, SmsSender};
+use async_trait::async_trait;
+
+// Sends through the 46elks API, https://46elks.com/docs/send-sms. The base URL is configurable so
+// that a local mock server can stand in for the provider.
+pub struct Elks {
+    http: reqwest::Client,
+    url: String,
+    username: String,
+    password: String,
+    sender: String,
+}
+
+impl Elks {
+    pub fn new(url: String, username: String, password: String, sender: String) -> Self {
+        Elks {
+            http: reqwest::Client::new(),
+            url,
+            username,
+            password,
+            sender,
+        }
+    }
+}
+
+#[async_trait]
+impl SmsSender for Elks {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SendError> {
+        let response = self
+            .http
+            .post(&format!("{}/a1/sms", self.url.trim_end_matches('/'))[..])
+            .basic_auth(&self.username, Some(&self.password))
+            .form(&[("from", &self.sender[..]), ("to", to), ("message", message)])
+            .send()
+            .await
+            .map_err(SendError::Request)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(SendError::Status(response.status().as_u16()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use warp::http::StatusCode;
+    use warp::Filter;
+
+    fn elks(url: String) -> Elks {
+        let (username, password) = ("user".to_string(), "pass".to_string());
+        Elks::new(url, username, password, "Fiordland".to_string())
+    }
+
+    #[tokio::test]
+    async fn posts_form_to_provider() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = requests.clone();
+        let route = warp::post()
+            .and(warp::path!("a1" / "sms"))
+            .and(warp::header::<String>("authorization"))
+            .and(warp::body::form())
+            .map(move |auth: String, form: HashMap<String, String>| {
+                recorded.lock().unwrap().push((auth, form));
+                warp::reply()
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let sender = elks(format!("http://{}/", addr));
+        let result = sender.send("+46701234567", "Route saved.").await;
+
+        assert!(result.is_ok());
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (auth, form) = &requests[0];
+        assert_eq!(auth, "Basic dXNlcjpwYXNz");
+        assert_eq!(form["from"], "Fiordland");
+        assert_eq!(form["to"], "+46701234567");
+        assert_eq!(form["message"], "Route saved.");
+    }
+
+    #[tokio::test]
+    async fn reports_provider_status() {
+        let route = warp::any()
+            .map(|| warp::reply::with_status(warp::reply(), StatusCode::SERVICE_UNAVAILABLE));
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let sender = elks(format!("http://{}", addr));
+        let result = sender.send("+46701234567", "Route saved.").await;
+
+        assert!(matches!(result, Err(SendError::Status(503))));
+    }
+}
@@ -0,0 +1,13 @@
+use super::{SendError, SmsSender};
+use async_trait::async_trait;
+
+// Used when no provider is configured; messages are only written to the log.
+pub struct Log;
+
+#[async_trait]
+impl SmsSender for Log {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SendError> {
+        println!("SMS to {}: {}", to, message);
+        Ok(())
+    }
+}
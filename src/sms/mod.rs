@@ -0,0 +1,98 @@
+use crate::error::Error;
+use async_trait::async_trait;
+use std::time::Duration;
+
+mod elks;
+mod log;
+#[cfg(test)]
+mod recorder;
+
+pub use elks::Elks;
+pub use log::Log;
+#[cfg(test)]
+pub use recorder::Recorder;
+
+const ATTEMPTS: u32 = 4;
+const BACKOFF: Duration = Duration::from_secs(2);
+
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SendError>;
+}
+
+#[derive(Debug)]
+pub enum SendError {
+    Request(reqwest::Error),
+    Status(u16),
+}
+
+// A send that failed on every attempt, with the error of the last one.
+pub struct Failure {
+    pub error: SendError,
+    pub attempts: u32,
+}
+
+// Retries a failed send with exponential backoff, i.e. waiting 2, 4 and 8 seconds between attempts.
+pub async fn send_with_retry(
+    sender: &dyn SmsSender,
+    to: &str,
+    message: &str,
+) -> Result<(), Failure> {
+    let mut delay = BACKOFF;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match sender.send(to, message).await {
+            Ok(()) => return Ok(()),
+            Err(error) if attempts >= ATTEMPTS => return Err(Failure { error, attempts }),
+            Err(error) => {
+                eprintln!("Could not send SMS, retrying: {}", error.description());
+                tokio::time::delay_for(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+impl Error for SendError {
+    fn description(&self) -> String {
+        match self {
+            SendError::Request(err) => format!("Request failed: {}", err),
+            SendError::Status(status) => format!("Provider responded with status {}", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn retries_until_sent() {
+        tokio::time::pause();
+        let recorder = Recorder::failing(2);
+
+        let result = send_with_retry(&recorder, "+46701234567", "Route saved.").await;
+
+        assert!(result.is_ok());
+        let sent = vec![("+46701234567".to_string(), "Route saved.".to_string())];
+        assert_eq!(recorder.sent(), sent);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_attempts() {
+        tokio::time::pause();
+        let recorder = Recorder::failing(ATTEMPTS);
+
+        let result = send_with_retry(&recorder, "+46701234567", "Route saved.").await;
+
+        match result {
+            Err(Failure {
+                error: SendError::Status(503),
+                attempts,
+            }) => assert_eq!(attempts, ATTEMPTS),
+            _ => panic!("expected the send to fail"),
+        }
+        assert!(recorder.sent().is_empty());
+    }
+}
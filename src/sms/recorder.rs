@@ -0,0 +1,40 @@
+use super::{SendError, SmsSender};
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+// Keeps every sent message in memory so that tests can inspect what would have been sent. The
+// given number of sends fail first, to exercise retries.
+#[derive(Default)]
+pub struct Recorder {
+    sent: Mutex<Vec<(String, String)>>,
+    failures: Mutex<u32>,
+}
+
+impl Recorder {
+    pub fn failing(failures: u32) -> Self {
+        Recorder {
+            sent: Mutex::new(vec![]),
+            failures: Mutex::new(failures),
+        }
+    }
+
+    pub fn sent(&self) -> Vec<(String, String)> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl SmsSender for Recorder {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SendError> {
+        let mut failures = self.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return Err(SendError::Status(503));
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((to.to_string(), message.to_string()));
+        Ok(())
+    }
+}
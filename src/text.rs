@@ -0,0 +1,35 @@
+// Folds case and diacritics, so that words match however they were typed. Phones without Nordic
+// keyboards tend to leave out the diacritics rather than spell them out.
+pub fn fold(word: &str) -> String {
+    let mut folded = String::with_capacity(word.len());
+    for c in word.trim().to_lowercase().chars() {
+        match c {
+            'å' | 'ä' | 'á' | 'à' | 'â' => folded.push('a'),
+            'ö' | 'ø' | 'ó' | 'ò' | 'ô' => folded.push('o'),
+            'é' | 'è' | 'ê' | 'ë' => folded.push('e'),
+            'ü' | 'ú' => folded.push('u'),
+            'æ' => folded.push_str("ae"),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+// Undoes what phone keyboards make of typed punctuation and spaces: Unicode whitespace such as
+// no-break spaces becomes a space, dashes become hyphens and curly apostrophes straight ones.
+// Zero-width characters are dropped. Typographic quotes are left to the parser, as they pair up.
+pub fn normalise(message: &str) -> String {
+    let mut normalised = String::with_capacity(message.len());
+    for c in message.chars() {
+        match c {
+            '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' => (),
+            c if c.is_whitespace() && !c.is_ascii_whitespace() => normalised.push(' '),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2212}' => {
+                normalised.push('-')
+            }
+            '\u{2018}' | '\u{2019}' | '\u{201b}' => normalised.push('\''),
+            c => normalised.push(c),
+        }
+    }
+    normalised
+}
@@ -3,6 +3,10 @@ import Feature from "ol/Feature";
 import * as Popup from "./ol/popup";
 let phone = new URL(window.location.href).searchParams.get("map");
 let ws: WebSocket;
+// A distress call is highlighted until the viewer acknowledges it by clicking the page, or at
+// most this long.
+const SOS_HIGHLIGHT_MS = 10 * 60 * 1000;
+let sosTimeout: number;
 if (!/^\d{5,30}$/.test(phone)) {
     window.location.href = "/";
 }
@@ -10,12 +14,13 @@ if (!/^\d{5,30}$/.test(phone)) {
 document.addEventListener('DOMContentLoaded', () => {
     let ol = Ol.initMap();
     wsHandler(ol)
+    document.addEventListener('click', clearSos);
 
     ol.map.on('singleclick', (event) => {
         let handled = false;
         ol.map.forEachFeatureAtPixel(event.pixel, (feature: Feature, layer) => {
             if (layer == ol.traceLayer) {
-                Popup.preparePopup('Checkin', feature, ol);
+                Popup.preparePopup(feature.get('sos') ? 'SOS' : 'Checkin', feature, ol);
             } else if (layer == ol.routeLayer) {
                 Popup.preparePopup('Planning', feature, ol);
             }
@@ -50,5 +55,19 @@ function wsHandler(ol: Ol.OlObjects) {
         }
         Ol.loadGeoJson(json["route"], ol.routeLayer);
         Ol.loadGeoJson(json["trace"], ol.traceLayer);
+        if (json["event"] == "sos") {
+            highlightSos();
+        }
     }
+}
+
+function highlightSos() {
+    document.body.classList.add("sos");
+    window.clearTimeout(sosTimeout);
+    sosTimeout = window.setTimeout(clearSos, SOS_HIGHLIGHT_MS);
+}
+
+function clearSos() {
+    window.clearTimeout(sosTimeout);
+    document.body.classList.remove("sos");
 }
\ No newline at end of file
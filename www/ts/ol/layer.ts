@@ -21,6 +21,10 @@ import Fill from "ol/style/Fill";
 import Stroke from "ol/style/Stroke";
 import CircleStyle from "ol/style/Circle";
 import ImageStyle from "ol/style/Image";
+import {FeatureLike} from "ol/Feature";
+import Geometry from "ol/geom/Geometry";
+import Point from "ol/geom/Point";
+import {fromExtent} from "ol/geom/Polygon";
 
 const EXP_TIMEOUT = 500;
 const ATTR_LM = [
@@ -196,22 +200,82 @@ function createVectorLayer(color: string, zIndex: number): VectorImageLayer {
         source: new Vector({
             wrapX: false,
         }),
-        style: new Style({
-            image: new CircleStyle({
-                radius: 10,
+        style: [
+            new Style({
+                image: new CircleStyle({
+                    radius: 10,
+                    stroke: new Stroke({
+                        color,
+                        width: 3,
+                    }),
+                    fill: new Fill({
+                        color: [180, 180, 180, 0.5],
+                    }),
+                }) as ImageStyle,
+            }),
+            new Style({
+                geometry: precisionSquare_,
                 stroke: new Stroke({
                     color,
-                    width: 3,
+                    width: 1,
                 }),
                 fill: new Fill({
-                    color: [180, 180, 180, 0.5],
+                    color: [180, 180, 180, 0.2],
                 }),
-            }) as ImageStyle,
-        }),
+            }),
+            new Style({
+                geometry: sosPoint_,
+                image: new CircleStyle({
+                    radius: 16,
+                    stroke: new Stroke({
+                        color: '#d00',
+                        width: 4,
+                    }),
+                }) as ImageStyle,
+            }),
+            new Style({
+                geometry: statePoint_("reached"),
+                image: new CircleStyle({
+                    radius: 5,
+                    fill: new Fill({
+                        color: '#080',
+                    }),
+                }) as ImageStyle,
+            }),
+            new Style({
+                geometry: statePoint_("skipped"),
+                image: new CircleStyle({
+                    radius: 5,
+                    fill: new Fill({
+                        color: '#666',
+                    }),
+                }) as ImageStyle,
+            }),
+        ],
         zIndex,
     });
 }
 
+function precisionSquare_(feature: FeatureLike): Geometry {
+    let precision = feature.get("precision");
+    if (!precision) return undefined;
+    let [x, y] = (feature.getGeometry() as Point).getCoordinates();
+    let half = precision / 2;
+    return fromExtent([x - half, y - half, x + half, y + half]);
+}
+
+function sosPoint_(feature: FeatureLike): Geometry {
+    if (!feature.get("sos")) return undefined;
+    return feature.getGeometry() as Point;
+}
+
+function statePoint_(state: string): (feature: FeatureLike) => Geometry {
+    return (feature: FeatureLike) => {
+        if (feature.get("state") != state) return undefined;
+        return feature.getGeometry() as Point;
+    };
+}
+
 
 export {
     createMap,
@@ -47,7 +47,10 @@ function formatEventInfo_(header: string, event: Feature): HTMLDivElement {
     let date = event.get("date");
     let time = event.get("time");
     let message = event.get("message");
+    let precision = event.get("precision");
+    let place = event.get("place");
 
+    if (place) htmlText.push(`${title("Place:")} ${place}`);
     if (date && time) {
         htmlText.push(`${title("Date:")} ${date} ${time}`);
     } else if (date) {
@@ -56,6 +59,7 @@ function formatEventInfo_(header: string, event: Feature): HTMLDivElement {
         htmlText.push(`${title("Date:")} ${time}`);
     }
     if (message) htmlText.push(`${title("Message:")} ${message}`);
+    if (precision) htmlText.push(`${title("Precision:")} ${precision} m`);
 
     container.innerHTML = `<h3>${header}</h3>` + htmlText.join("<br>\n") + "<br>\n";
 